@@ -1,6 +1,16 @@
-#[derive(Debug, PartialEq)]
+use super::span::Span;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Syntax {
+    V2,
     V3,
+    Edition(Edition),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Edition {
+    Edition2023,
+    Edition2024,
 }
 
 #[derive(Debug, PartialEq)]
@@ -14,16 +24,30 @@ pub enum ImportType {
 pub struct Import {
     pub import_type: ImportType,
     pub name: String,
+    pub span: Span,
+    pub comments: Comments,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct FullIdent {
     pub idents: Vec<String>,
+    //from the first to the last ident, without a leading dot
+    pub span: Span,
+}
+
+//names are equal if their idents are, wherever they are in the source
+impl PartialEq for FullIdent {
+    fn eq(&self, other: &FullIdent) -> bool {
+        return self.idents == other.idents;
+    }
 }
 
 impl FullIdent {
     pub fn new(v: Vec<String>) -> FullIdent {
-        return FullIdent { idents: v };
+        return FullIdent {
+            idents: v,
+            span: Span::default(),
+        };
     }
 
     pub fn insert(&mut self, ix: usize, n: String) {
@@ -34,47 +58,330 @@ impl FullIdent {
 #[derive(Debug)]
 pub struct Package {
     pub full_ident: FullIdent,
+    pub span: Span,
+    pub comments: Comments,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ProtoOption {
-    pub full_ident: FullIdent,
+    //the dot separated parts of the name, e.g. features, (pb.cpp) and
+    //legacy_closed_enum for features.(pb.cpp).legacy_closed_enum
+    pub name: Vec<OptionNamePart>,
     pub constant: ConstantValue,
+    pub span: Span,
+    //those of the option statement, empty for options in [brackets]
+    pub comments: Comments,
 }
 
-#[derive(Debug, PartialEq)]
+impl ProtoOption {
+    //true for the built-in option with the dotted name, e.g. "deprecated"
+    //or "features.field_presence"
+    pub fn is_named(&self, name: &str) -> bool {
+        let mut names = name.split('.');
+        let matches = self
+            .name
+            .iter()
+            .all(|part| !part.extension && names.next() == Some(&part.name.idents[0][..]));
+        return matches && names.next().is_none();
+    }
+
+    //the name as written, e.g. (my.ext).sub.field
+    pub fn name_text(&self) -> String {
+        let parts: Vec<String> = self
+            .name
+            .iter()
+            .map(|part| {
+                let name = part.name.idents.join(".");
+                match (part.extension, part.fully_qualified) {
+                    (false, _) => name,
+                    (true, false) => format!("({})", name),
+                    (true, true) => format!("(.{})", name),
+                }
+            })
+            .collect();
+        return parts.join(".");
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct OptionNamePart {
+    //a single ident, unless the part is an extension
+    pub name: FullIdent,
+    //true if the part was in parentheses, i.e. names an extension (custom
+    //option or language specific feature)
+    pub extension: bool,
+    //true if the extension name started with a leading dot
+    pub fully_qualified: bool,
+}
+
+impl OptionNamePart {
+    pub fn new(ident: &str) -> OptionNamePart {
+        return OptionNamePart {
+            name: FullIdent::new(vec![ident.to_string()]),
+            extension: false,
+            fully_qualified: false,
+        };
+    }
+
+    pub fn extension(idents: &[&str]) -> OptionNamePart {
+        return OptionNamePart {
+            name: FullIdent::new(idents.iter().map(|s| s.to_string()).collect()),
+            extension: true,
+            fully_qualified: false,
+        };
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum ConstantValue {
     IdentValue(FullIdent),
-    NumberValue(f32),
+    //exact integer value, covers the whole int64 and uint64 range
+    IntValue(i128),
+    FloatValue(f64),
     StringValue(String),
+    //string literal with escapes that aren't valid UTF-8, e.g. "\xff"
+    BytesValue(Vec<u8>),
     BoolValue(bool),
+    //message literal in protobuf text format, e.g. { get: "/v1/foo" body: "*" }
+    AggregateValue(Vec<AggregateField>),
+    //only allowed inside of an aggregate value
+    ListValue(Vec<ConstantValue>),
+}
+
+impl ConstantValue {
+    //the raw bytes of a string literal, e.g. for bytes field defaults
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            ConstantValue::StringValue(s) => Some(s.as_bytes()),
+            ConstantValue::BytesValue(b) => Some(b),
+            _ => None,
+        }
+    }
+}
+
+//field of an aggregate value, repeated keys are kept as separate fields in order
+#[derive(Debug, PartialEq, Clone)]
+pub struct AggregateField {
+    pub name: FullIdent,
+    //true if the name was an extension in brackets, e.g. [foo.bar]
+    pub extension: bool,
+    pub value: ConstantValue,
+    pub span: Span,
+}
+
+//comments attached to a declaration, using protoc's rules: the leading
+//comment is directly above the declaration, the trailing comment follows it
+//on the same line or directly below. Detached comments are above the leading
+//comment, separated by blank lines. Line comments keep the text after // up
+//to and including the newline, block comments the text between /* and */.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Comments {
+    pub leading: Option<String>,
+    pub trailing: Option<String>,
+    pub detached: Vec<String>,
 }
 
 #[derive(Debug)]
 pub struct Service {
     pub name: String,
     pub rpcs: Vec<Rpc>,
+    pub options: Vec<ProtoOption>,
+    pub span: Span,
+    pub comments: Comments,
 }
 
 #[derive(Debug)]
 pub struct Rpc {
     pub name: String,
     pub request_type: FullIdent,
+    //true if the request type started with a leading dot
+    pub request_fully_qualified: bool,
     pub response_type: FullIdent,
+    pub response_fully_qualified: bool,
+    pub client_streaming: bool,
+    pub server_streaming: bool,
+    pub options: Vec<ProtoOption>,
+    pub span: Span,
+    pub comments: Comments,
 }
 
 #[derive(Debug)]
 pub struct Message {
     pub name: String,
     pub fields: Vec<Field>,
+    pub nested_messages: Vec<Message>,
+    pub nested_enums: Vec<Enum>,
+    pub oneofs: Vec<OneOf>,
+    pub reserved: Vec<Reserved>,
+    pub options: Vec<ProtoOption>,
+    pub extensions: Vec<Extensions>,
+    pub extends: Vec<Extend>,
+    pub span: Span,
+    pub comments: Comments,
+}
+
+impl Message {
+    //the oneofs protoc synthesizes for the proto3 optional fields, in field
+    //declaration order. Descriptors list them after all real oneofs.
+    pub fn synthetic_oneofs(&self) -> Vec<SyntheticOneOf<'_>> {
+        let mut names: Vec<String> = self.fields.iter().map(|f| f.name.clone()).collect();
+        names.extend(self.oneofs.iter().map(|o| o.name.clone()));
+        names.extend(
+            self.oneofs
+                .iter()
+                .flat_map(|o| o.fields.iter())
+                .map(|f| f.name.clone()),
+        );
+
+        let mut synthetic = Vec::new();
+        for field in self.fields.iter().filter(|f| f.proto3_optional) {
+            let mut name = field.name.clone();
+            if !name.starts_with('_') {
+                name.insert(0, '_');
+            }
+            while names.contains(&name) {
+                name.insert(0, 'X');
+            }
+            names.push(name.clone());
+            synthetic.push(SyntheticOneOf { name, field });
+        }
+        return synthetic;
+    }
+}
+
+//oneof containing only a proto3 optional field
+#[derive(Debug)]
+pub struct SyntheticOneOf<'a> {
+    pub name: String,
+    pub field: &'a Field,
+}
+
+//a single extensions statement, its options apply to all of its ranges
+#[derive(Debug)]
+pub struct Extensions {
+    pub ranges: Vec<ExtensionRange>,
+    pub options: Vec<ProtoOption>,
+    pub span: Span,
+    pub comments: Comments,
+}
+
+impl Extensions {
+    pub fn contains(&self, n: i64) -> bool {
+        return self.ranges.iter().any(|r| r.contains(n));
+    }
+}
+
+//inclusive range, a single number has start == end
+#[derive(Debug)]
+pub struct ExtensionRange {
+    pub start: i32,
+    pub end: i32,
+    pub span: Span,
+}
+
+impl ExtensionRange {
+    pub fn contains(&self, n: i64) -> bool {
+        return self.start as i64 <= n && n <= self.end as i64;
+    }
+}
+
+#[derive(Debug)]
+pub struct Extend {
+    pub extendee: FullIdent,
+    //true if the extendee started with a leading dot
+    pub fully_qualified: bool,
+    pub fields: Vec<Field>,
+    pub span: Span,
+    pub comments: Comments,
+}
+
+#[derive(Debug)]
+pub struct OneOf {
+    pub name: String,
+    pub fields: Vec<Field>,
+    pub options: Vec<ProtoOption>,
+    pub span: Span,
+    pub comments: Comments,
 }
 
 #[derive(Debug)]
 pub struct Field {
     pub name: String,
     pub field_type: Type,
-    pub repeated: bool,
+    pub label: Option<FieldLabel>,
     pub field_number: u32,
+    //the [default = ...] value, only allowed in proto2
+    pub default: Option<ConstantValue>,
+    pub options: Vec<ProtoOption>,
+    //proto3 optional field with explicit presence
+    pub proto3_optional: bool,
+    pub span: Span,
+    pub comments: Comments,
+}
+
+impl Field {
+    pub fn is_repeated(&self) -> bool {
+        return self.label == Some(FieldLabel::Repeated);
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FieldLabel {
+    Required,
+    Optional,
+    Repeated,
+}
+
+#[derive(Debug)]
+pub struct Enum {
+    pub name: String,
+    pub values: Vec<EnumValue>,
+    pub reserved: Vec<Reserved>,
+    pub options: Vec<ProtoOption>,
+    pub span: Span,
+    pub comments: Comments,
+}
+
+#[derive(Debug)]
+pub struct EnumValue {
+    pub name: String,
+    pub number: i32,
+    pub options: Vec<ProtoOption>,
+    pub span: Span,
+    pub comments: Comments,
+}
+
+//a single reserved statement, it has either ranges or names
+#[derive(Debug, PartialEq)]
+pub struct Reserved {
+    pub ranges: Vec<ReservedRange>,
+    pub names: Vec<String>,
+    pub span: Span,
+    pub comments: Comments,
+}
+
+impl Reserved {
+    pub fn contains_number(&self, n: i64) -> bool {
+        return self.ranges.iter().any(|r| r.contains(n));
+    }
+
+    pub fn contains_name(&self, name: &str) -> bool {
+        return self.names.iter().any(|n| n == name);
+    }
+}
+
+//inclusive range, a single number has start == end
+#[derive(Debug, PartialEq)]
+pub struct ReservedRange {
+    pub start: i32,
+    pub end: i32,
+    pub span: Span,
+}
+
+impl ReservedRange {
+    pub fn contains(&self, n: i64) -> bool {
+        return self.start as i64 <= n && n <= self.end as i64;
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -94,27 +401,47 @@ pub enum Type {
     Bool,
     String,
     Bytes,
+    //message or enum type, fully_qualified if it started with a leading dot
+    Reference {
+        full_ident: FullIdent,
+        fully_qualified: bool,
+    },
+    //map<key, value>, the key is always an integral or string type
+    Map {
+        key: Box<Type>,
+        value: Box<Type>,
+    },
+    //proto2 group, the group message with this name is a nested message
+    //of the message declaring the group field
+    Group(String),
 }
 
 #[derive(Debug)]
 pub struct ProtoDef {
     pub syntax: Syntax,
+    //comments of the syntax or edition statement, e.g. a license header
+    pub comments: Comments,
     pub imports: Vec<Import>,
     pub packages: Vec<Package>,
     pub options: Vec<ProtoOption>,
     pub services: Vec<Service>,
     pub messages: Vec<Message>,
+    pub enums: Vec<Enum>,
+    pub extends: Vec<Extend>,
 }
 
 impl ProtoDef {
     pub fn new(syn: Syntax) -> ProtoDef {
         return ProtoDef {
             syntax: syn,
+            comments: Comments::default(),
             imports: Vec::new(),
             packages: Vec::new(),
             options: Vec::new(),
             services: Vec::new(),
             messages: Vec::new(),
+            enums: Vec::new(),
+            extends: Vec::new(),
         };
     }
 
@@ -137,4 +464,12 @@ impl ProtoDef {
     pub fn add_message(&mut self, message: Message) {
         self.messages.push(message);
     }
+
+    pub fn add_enum(&mut self, enum_def: Enum) {
+        self.enums.push(enum_def);
+    }
+
+    pub fn add_extend(&mut self, extend: Extend) {
+        self.extends.push(extend);
+    }
 }
@@ -0,0 +1,292 @@
+use std::fmt;
+
+use super::span::Span;
+
+//lossless syntax tree of a file. Every byte of the source is in exactly one
+//token, including whitespace, comments and text that could not be scanned,
+//so printing the tree of an unmodified file reproduces it byte for byte.
+//
+//The tree is not a layer beneath the parser, the ast is not derived from
+//it. The scanner records it next to the ast while parsing, and only for
+//parser::parse_cst, parse and parse_recovering don't build it.
+//
+//Statements are nodes, their tokens and nested statements are the children.
+//Whitespace and comments between two statements belong to the enclosing
+//node, e.g. the comment above a field is a child of the message.
+//
+//A statement node has the span of the ast node parsed from it, find gets
+//the node of e.g. a field with find(NodeKind::Field, field.span), the
+//message of a group is its Field node. The spans are those of the source the
+//tree was built from, editing the tokens does not update them.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NodeKind {
+    File,
+    Syntax,
+    Edition,
+    Import,
+    Package,
+    Option,
+    Message,
+    Field,
+    OneOf,
+    Enum,
+    EnumValue,
+    Service,
+    Rpc,
+    Reserved,
+    Extensions,
+    Extend,
+    //statement that failed to parse
+    Error,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TokenKind {
+    Whitespace,
+    Comment,
+    Keyword,
+    Ident,
+    //string, number or bool literal
+    Literal,
+    Symbol,
+    //text that could not be scanned, e.g. an unterminated string
+    Error,
+}
+
+impl TokenKind {
+    pub fn is_trivia(&self) -> bool {
+        return *self == TokenKind::Whitespace || *self == TokenKind::Comment;
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CstToken {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Element {
+    Node(Node),
+    Token(CstToken),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub children: Vec<Element>,
+    //from the first to the last token of the node
+    pub span: Span,
+}
+
+impl Node {
+    pub fn new(kind: NodeKind) -> Node {
+        return Node {
+            kind,
+            children: Vec::new(),
+            span: Span::default(),
+        };
+    }
+
+    //the innermost node of the kind with exactly this span, which is the
+    //node an ast node with this span was parsed from
+    pub fn find(&self, kind: NodeKind, span: Span) -> Option<&Node> {
+        if !contains(self.span, span) {
+            return None;
+        }
+        for node in self.nodes() {
+            if let Some(found) = node.find(kind, span) {
+                return Some(found);
+            }
+        }
+        if self.kind == kind && self.span == span {
+            return Some(self);
+        }
+        return None;
+    }
+
+    //like find, to edit the tokens of the node
+    pub fn find_mut(&mut self, kind: NodeKind, span: Span) -> Option<&mut Node> {
+        if !contains(self.span, span) {
+            return None;
+        }
+        if self.kind == kind
+            && self.span == span
+            && !self.nodes().any(|n| n.find(kind, span).is_some())
+        {
+            return Some(self);
+        }
+        for child in self.children.iter_mut() {
+            if let Element::Node(node) = child
+                && let Some(found) = node.find_mut(kind, span)
+            {
+                return Some(found);
+            }
+        }
+        return None;
+    }
+
+    //the child nodes, without tokens
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        return self.children.iter().filter_map(|c| match c {
+            Element::Node(node) => Some(node),
+            Element::Token(_) => None,
+        });
+    }
+
+    //the tokens of the node and all its descendants in source order
+    pub fn tokens(&self) -> Vec<&CstToken> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        return tokens;
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a CstToken>) {
+        for child in self.children.iter() {
+            match child {
+                Element::Node(node) => node.collect_tokens(tokens),
+                Element::Token(token) => tokens.push(token),
+            }
+        }
+    }
+}
+
+fn contains(outer: Span, inner: Span) -> bool {
+    return outer.start <= inner.start && inner.end <= outer.end;
+}
+
+//prints the source text of the node
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.tokens() {
+            f.write_str(&token.text)?;
+        }
+        return Ok(());
+    }
+}
+
+//builds the tree while the scanner reads the tokens. Trivia is held back
+//until the next token, so it ends up between the statements instead of at
+//the end of the statement before. The text added must be the source in
+//order, the spans are computed from it.
+pub struct Builder {
+    //the open nodes, the file node is at the bottom
+    stack: Vec<Node>,
+    trivia: Vec<CstToken>,
+    //position of the next text added
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        return Builder::new();
+    }
+}
+
+impl Builder {
+    pub fn new() -> Builder {
+        return Builder {
+            stack: vec![Node::new(NodeKind::File)],
+            trivia: Vec::new(),
+            offset: 0,
+            line: 1,
+            column: 1,
+        };
+    }
+
+    pub fn trivia(&mut self, kind: TokenKind, text: &str) {
+        let token = self.new_token(kind, text);
+        self.trivia.push(token);
+    }
+
+    pub fn token(&mut self, kind: TokenKind, text: &str) {
+        self.flush_trivia();
+        let token = self.new_token(kind, text);
+        self.top().children.push(Element::Token(token));
+    }
+
+    //opens a node that starts with the last token added
+    pub fn start_node(&mut self, kind: NodeKind) {
+        let mut node = Node::new(kind);
+        let parent = self.top();
+        if let Some(Element::Token(t)) = parent.children.last()
+            && !t.kind.is_trivia()
+        {
+            node.children.extend(parent.children.pop());
+        }
+        self.stack.push(node);
+    }
+
+    //number of open nodes, including the file node
+    pub fn depth(&self) -> usize {
+        return self.stack.len();
+    }
+
+    //closes the open nodes until depth nodes are left, the file node is
+    //never closed
+    pub fn finish_nodes(&mut self, depth: usize) {
+        while self.stack.len() > depth.max(1) {
+            let mut node = self.stack.pop().unwrap();
+            node.span = self.children_span(&node);
+            self.top().children.push(Element::Node(node));
+        }
+    }
+
+    //closes all nodes and returns the file node
+    pub fn finish(mut self) -> Node {
+        self.finish_nodes(1);
+        self.flush_trivia();
+        let mut file = self.stack.pop().unwrap();
+        file.span = Span::new(0, self.offset, 1, 1);
+        return file;
+    }
+
+    fn new_token(&mut self, kind: TokenKind, text: &str) -> CstToken {
+        let span = Span::new(
+            self.offset,
+            self.offset + text.len(),
+            self.line,
+            self.column,
+        );
+        self.offset = span.end;
+        for c in text.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        return CstToken {
+            kind,
+            text: text.to_string(),
+            span,
+        };
+    }
+
+    //from the start of the first to the end of the last child, an empty
+    //node is at the position of the next text
+    fn children_span(&self, node: &Node) -> Span {
+        let span = |element: &Element| match element {
+            Element::Node(node) => node.span,
+            Element::Token(token) => token.span,
+        };
+        match (node.children.first(), node.children.last()) {
+            (Some(first), Some(last)) => return span(first).to(span(last)),
+            _ => return Span::new(self.offset, self.offset, self.line, self.column),
+        }
+    }
+
+    fn flush_trivia(&mut self) {
+        let trivia: Vec<Element> = self.trivia.drain(..).map(Element::Token).collect();
+        self.top().children.extend(trivia);
+    }
+
+    fn top(&mut self) -> &mut Node {
+        return self.stack.last_mut().unwrap();
+    }
+}
@@ -1,10 +1,167 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use super::span::Span;
+
 #[derive(PartialEq, Debug)]
-pub struct ProtoParseError {
-    message: String,
+pub enum ProtoParseError {
+    //invalid characters, literals, escapes or comments
+    Lexical {
+        message: String,
+        span: Option<Span>,
+    },
+    //found is the description of the token, expected the descriptions of
+    //the tokens that would have been accepted, e.g. "`;`" or "identifier"
+    UnexpectedToken {
+        found: String,
+        expected: Vec<String>,
+        span: Option<Span>,
+    },
+    Io {
+        path: PathBuf,
+        kind: io::ErrorKind,
+    },
+    //syntactically fine but not allowed, e.g. a reserved field number
+    Semantic {
+        message: String,
+        span: Option<Span>,
+    },
+}
+
+impl ProtoParseError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ProtoParseError::Lexical { span, .. } => *span,
+            ProtoParseError::UnexpectedToken { span, .. } => *span,
+            ProtoParseError::Io { .. } => None,
+            ProtoParseError::Semantic { span, .. } => *span,
+        }
+    }
+
+    //sets the span, unless the error already points to a location
+    pub fn or_span(mut self, new_span: Span) -> ProtoParseError {
+        match &mut self {
+            ProtoParseError::Lexical { span, .. }
+            | ProtoParseError::UnexpectedToken { span, .. }
+            | ProtoParseError::Semantic { span, .. } => {
+                if span.is_none() {
+                    *span = Some(new_span);
+                }
+            }
+            ProtoParseError::Io { .. } => {}
+        }
+        return self;
+    }
+
+    //renders the error like rustc does, with the offending line of source
+    //and a caret under the span, e.g.
+    //
+    //error: unexpected `}`, expected `;`
+    // --> foo.proto:4:1
+    //  |
+    //4 | }
+    //  | ^
+    pub fn render(&self, source: &str, file_name: &str) -> String {
+        let mut out = format!("error: {}\n", self);
+        let span = match self.span() {
+            Some(span) => span,
+            None => {
+                if let ProtoParseError::Io { path, .. } = self {
+                    out.push_str(&format!(" --> {}\n", path.display()));
+                } else {
+                    out.push_str(&format!(" --> {}\n", file_name));
+                }
+                return out;
+            }
+        };
+
+        let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[span.start..]
+            .find('\n')
+            .map_or(source.len(), |i| span.start + i);
+        let line = source[line_start..line_end].trim_end_matches('\r');
+
+        //keep tabs so the caret lines up with the source line
+        let indent: String = source[line_start..span.start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        //an error at the end of a line ending in a lone \r starts after the
+        //trimmed line
+        let line_end = (line_start + line.len()).max(span.start);
+        let underline_end = span.end.clamp(span.start, line_end);
+        let carets = source[span.start..underline_end].chars().count().max(1);
+
+        let number = span.line.to_string();
+        let gutter = " ".repeat(number.len());
+        out.push_str(&format!(
+            "{}--> {}:{}:{}\n",
+            gutter, file_name, span.line, span.column
+        ));
+        out.push_str(&format!("{} |\n", gutter));
+        out.push_str(&format!("{} | {}\n", number, line));
+        out.push_str(&format!("{} | {}{}\n", gutter, indent, "^".repeat(carets)));
+        return out;
+    }
 }
 
+impl fmt::Display for ProtoParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtoParseError::Lexical { message, .. } => write!(f, "lexical error: {}", message),
+            ProtoParseError::UnexpectedToken {
+                found, expected, ..
+            } => {
+                write!(f, "unexpected {}", found)?;
+                match expected.len() {
+                    0 => Ok(()),
+                    1 => write!(f, ", expected {}", expected[0]),
+                    n => write!(
+                        f,
+                        ", expected one of {} or {}",
+                        expected[..n - 1].join(", "),
+                        expected[n - 1]
+                    ),
+                }
+            }
+            ProtoParseError::Io { path, kind } => {
+                write!(f, "cannot read {}: {}", path.display(), kind)
+            }
+            ProtoParseError::Semantic { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for ProtoParseError {}
+
 pub fn err<T>(message: &str) -> Result<T, ProtoParseError> {
-    return Err(ProtoParseError {
+    return Err(ProtoParseError::Semantic {
         message: message.to_string(),
+        span: None,
+    });
+}
+
+pub fn err_at<T>(span: Span, message: &str) -> Result<T, ProtoParseError> {
+    return Err(ProtoParseError::Semantic {
+        message: message.to_string(),
+        span: Some(span),
+    });
+}
+
+pub fn lexical_err<T>(message: &str) -> Result<T, ProtoParseError> {
+    return Err(ProtoParseError::Lexical {
+        message: message.to_string(),
+        span: None,
+    });
+}
+
+//expected lists the descriptions of the accepted tokens
+pub fn unexpected<T>(found: &impl fmt::Display, expected: &[&str]) -> Result<T, ProtoParseError> {
+    return Err(ProtoParseError::UnexpectedToken {
+        found: found.to_string(),
+        expected: expected.iter().map(|e| e.to_string()).collect(),
+        span: None,
     });
 }
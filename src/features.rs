@@ -0,0 +1,246 @@
+use super::ast::*;
+use super::error::{ProtoParseError, err};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FieldPresence {
+    Explicit,
+    Implicit,
+    LegacyRequired,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EnumType {
+    Open,
+    Closed,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RepeatedFieldEncoding {
+    Packed,
+    Expanded,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Utf8Validation {
+    Verify,
+    None,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MessageEncoding {
+    LengthPrefixed,
+    Delimited,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum JsonFormat {
+    Allow,
+    LegacyBestEffort,
+}
+
+//features set directly on an element, None is inherited from the parent
+#[derive(Debug, PartialEq, Default)]
+pub struct FeatureSet {
+    pub field_presence: Option<FieldPresence>,
+    pub enum_type: Option<EnumType>,
+    pub repeated_field_encoding: Option<RepeatedFieldEncoding>,
+    pub utf8_validation: Option<Utf8Validation>,
+    pub message_encoding: Option<MessageEncoding>,
+    pub json_format: Option<JsonFormat>,
+}
+
+pub fn is_feature_option(option: &ProtoOption) -> bool {
+    let first = &option.name[0];
+    return !first.extension && first.name.idents[0] == "features";
+}
+
+impl FeatureSet {
+    //collects the features.* options, both the
+    //option features.field_presence = EXPLICIT; and
+    //option features = { field_presence: EXPLICIT }; forms are supported.
+    //Language specific features like features.(pb.cpp).legacy_closed_enum
+    //are skipped.
+    pub fn from_options(options: &[ProtoOption]) -> Result<FeatureSet, ProtoParseError> {
+        let mut set = FeatureSet::default();
+        for option in options.iter().filter(|o| is_feature_option(o)) {
+            let parts = &option.name[1..];
+            if parts.first().is_some_and(|p| p.extension) {
+                continue;
+            }
+            if parts.len() == 1 {
+                set.set(&parts[0].name.idents[0], &option.constant)?;
+            } else if parts.is_empty() {
+                match &option.constant {
+                    ConstantValue::AggregateValue(fields) => {
+                        for field in fields.iter().filter(|f| !f.extension) {
+                            if field.name.idents.len() != 1 {
+                                return err("features: unsupported feature name");
+                            }
+                            set.set(&field.name.idents[0], &field.value)?;
+                        }
+                    }
+                    _ => return err("features: aggregate value expected"),
+                }
+            } else {
+                let name = option.name_text();
+                return err(&format!(
+                    "features: unknown feature {}",
+                    &name["features.".len()..]
+                ));
+            }
+        }
+        return Ok(set);
+    }
+
+    fn set(&mut self, name: &str, value: &ConstantValue) -> Result<(), ProtoParseError> {
+        let value_name = match value {
+            ConstantValue::IdentValue(ident) if ident.idents.len() == 1 => &ident.idents[0][..],
+            _ => return err(&format!("features.{}: enum value expected", name)),
+        };
+
+        match (name, value_name) {
+            ("field_presence", "EXPLICIT") => self.field_presence = Some(FieldPresence::Explicit),
+            ("field_presence", "IMPLICIT") => self.field_presence = Some(FieldPresence::Implicit),
+            ("field_presence", "LEGACY_REQUIRED") => {
+                self.field_presence = Some(FieldPresence::LegacyRequired)
+            }
+            ("enum_type", "OPEN") => self.enum_type = Some(EnumType::Open),
+            ("enum_type", "CLOSED") => self.enum_type = Some(EnumType::Closed),
+            ("repeated_field_encoding", "PACKED") => {
+                self.repeated_field_encoding = Some(RepeatedFieldEncoding::Packed)
+            }
+            ("repeated_field_encoding", "EXPANDED") => {
+                self.repeated_field_encoding = Some(RepeatedFieldEncoding::Expanded)
+            }
+            ("utf8_validation", "VERIFY") => self.utf8_validation = Some(Utf8Validation::Verify),
+            ("utf8_validation", "NONE") => self.utf8_validation = Some(Utf8Validation::None),
+            ("message_encoding", "LENGTH_PREFIXED") => {
+                self.message_encoding = Some(MessageEncoding::LengthPrefixed)
+            }
+            ("message_encoding", "DELIMITED") => {
+                self.message_encoding = Some(MessageEncoding::Delimited)
+            }
+            ("json_format", "ALLOW") => self.json_format = Some(JsonFormat::Allow),
+            ("json_format", "LEGACY_BEST_EFFORT") => {
+                self.json_format = Some(JsonFormat::LegacyBestEffort)
+            }
+            _ => {
+                return err(&format!(
+                    "features: unknown feature or value {} = {}",
+                    name, value_name
+                ));
+            }
+        }
+        return Ok(());
+    }
+}
+
+//the effective features of an element. Start with for_file and walk down
+//the element chain, e.g. for_file(&def)?.for_message(&m)?.for_field(&f)?
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ResolvedFeatures {
+    pub field_presence: FieldPresence,
+    pub enum_type: EnumType,
+    pub repeated_field_encoding: RepeatedFieldEncoding,
+    pub utf8_validation: Utf8Validation,
+    pub message_encoding: MessageEncoding,
+    pub json_format: JsonFormat,
+}
+
+impl ResolvedFeatures {
+    //the feature defaults of an edition, proto2 and proto3 are treated
+    //as the editions they are equivalent to
+    pub fn defaults(syntax: Syntax) -> ResolvedFeatures {
+        match syntax {
+            Syntax::V2 => ResolvedFeatures {
+                field_presence: FieldPresence::Explicit,
+                enum_type: EnumType::Closed,
+                repeated_field_encoding: RepeatedFieldEncoding::Expanded,
+                utf8_validation: Utf8Validation::None,
+                message_encoding: MessageEncoding::LengthPrefixed,
+                json_format: JsonFormat::LegacyBestEffort,
+            },
+            Syntax::V3 => ResolvedFeatures {
+                field_presence: FieldPresence::Implicit,
+                enum_type: EnumType::Open,
+                repeated_field_encoding: RepeatedFieldEncoding::Packed,
+                utf8_validation: Utf8Validation::Verify,
+                message_encoding: MessageEncoding::LengthPrefixed,
+                json_format: JsonFormat::Allow,
+            },
+            Syntax::Edition(_) => ResolvedFeatures {
+                field_presence: FieldPresence::Explicit,
+                enum_type: EnumType::Open,
+                repeated_field_encoding: RepeatedFieldEncoding::Packed,
+                utf8_validation: Utf8Validation::Verify,
+                message_encoding: MessageEncoding::LengthPrefixed,
+                json_format: JsonFormat::Allow,
+            },
+        }
+    }
+
+    pub fn for_file(def: &ProtoDef) -> Result<ResolvedFeatures, ProtoParseError> {
+        return ResolvedFeatures::defaults(def.syntax).with_options(&def.options);
+    }
+
+    pub fn for_message(&self, message: &Message) -> Result<ResolvedFeatures, ProtoParseError> {
+        return self.with_options(&message.options);
+    }
+
+    //fields of the oneof always have explicit presence, resolve them
+    //with for_field on the result
+    pub fn for_oneof(&self, oneof: &OneOf) -> Result<ResolvedFeatures, ProtoParseError> {
+        let mut resolved = self.with_options(&oneof.options)?;
+        resolved.field_presence = FieldPresence::Explicit;
+        return Ok(resolved);
+    }
+
+    pub fn for_enum(&self, enum_def: &Enum) -> Result<ResolvedFeatures, ProtoParseError> {
+        return self.with_options(&enum_def.options);
+    }
+
+    pub fn for_enum_value(&self, value: &EnumValue) -> Result<ResolvedFeatures, ProtoParseError> {
+        return self.with_options(&value.options);
+    }
+
+    //the proto2 required and optional labels and the packed option are taken
+    //into account like the equivalent features. Note that message typed
+    //fields always have explicit presence, regardless of field_presence.
+    pub fn for_field(&self, field: &Field) -> Result<ResolvedFeatures, ProtoParseError> {
+        let mut resolved = self.with_options(&field.options)?;
+        match field.label {
+            Some(FieldLabel::Required) => resolved.field_presence = FieldPresence::LegacyRequired,
+            Some(FieldLabel::Optional) => resolved.field_presence = FieldPresence::Explicit,
+            _ => {}
+        }
+        if let Some(packed) = field.options.iter().find(|o| o.is_named("packed")) {
+            resolved.repeated_field_encoding = match packed.constant {
+                ConstantValue::BoolValue(true) => RepeatedFieldEncoding::Packed,
+                ConstantValue::BoolValue(false) => RepeatedFieldEncoding::Expanded,
+                _ => return err("packed: true or false expected"),
+            };
+        }
+        if let Type::Group(_) = field.field_type {
+            resolved.message_encoding = MessageEncoding::Delimited;
+        }
+        return Ok(resolved);
+    }
+
+    pub fn merge(&self, features: &FeatureSet) -> ResolvedFeatures {
+        return ResolvedFeatures {
+            field_presence: features.field_presence.unwrap_or(self.field_presence),
+            enum_type: features.enum_type.unwrap_or(self.enum_type),
+            repeated_field_encoding: features
+                .repeated_field_encoding
+                .unwrap_or(self.repeated_field_encoding),
+            utf8_validation: features.utf8_validation.unwrap_or(self.utf8_validation),
+            message_encoding: features.message_encoding.unwrap_or(self.message_encoding),
+            json_format: features.json_format.unwrap_or(self.json_format),
+        };
+    }
+
+    fn with_options(&self, options: &[ProtoOption]) -> Result<ResolvedFeatures, ProtoParseError> {
+        let features = FeatureSet::from_options(options)?;
+        return Ok(self.merge(&features));
+    }
+}
@@ -1,10 +1,16 @@
 #![crate_name = "protoparse"]
 #![crate_type = "lib"]
+#![allow(clippy::needless_return)]
 
 #[macro_use]
 extern crate lazy_static;
 
 pub mod ast;
+pub mod cst;
 pub mod error;
+pub mod features;
 pub mod parser;
+pub mod printer;
 pub mod scanner;
+pub mod span;
+pub mod visit;
@@ -3,73 +3,255 @@ use std::io::Read;
 use std::path::Path;
 
 use super::ast::*;
-use super::error::{ProtoParseError, err};
-use super::scanner::{Scanner, Token};
+use super::cst::{Node, NodeKind};
+use super::error::{ProtoParseError, err, err_at, unexpected};
+use super::features::{FeatureSet, is_feature_option};
+use super::scanner::{Scanner, SpannedToken, Token};
+use super::span::Span;
 
-pub fn parse(buffer: &String) -> Result<ProtoDef, ProtoParseError> {
+const MAX_FIELD_NUMBER: i64 = 536_870_911;
+
+//inclusive start and end of a reserved or extension range and its span
+type Range = (i32, i32, Span);
+
+//fails on the first error, see parse_recovering for all errors
+pub fn parse(buffer: &str) -> Result<ProtoDef, ProtoParseError> {
+    let (def, mut diagnostics) = parse_recovering(buffer);
+    if diagnostics.is_empty() {
+        return Ok(def);
+    }
+    return Err(diagnostics.remove(0));
+}
+
+//parses as much of the file as possible. Failing statements are skipped and
+//reported, the diagnostics are empty if the file is valid.
+pub fn parse_recovering(buffer: &str) -> (ProtoDef, Vec<ProtoParseError>) {
     let mut scanner = Scanner::new(buffer);
+    return parse_checked(&mut scanner);
+}
 
-    let syn = parse_syntax(&mut scanner)?;
-    let mut def = ProtoDef::new(syn);
+//like parse_recovering, also returns the lossless syntax tree the tokens of
+//the ast were read from. Printing the tree gives back the buffer unchanged,
+//Node::find gets the node of an ast node by its span.
+pub fn parse_cst(buffer: &str) -> (Node, ProtoDef, Vec<ProtoParseError>) {
+    let mut scanner = Scanner::with_cst(buffer);
+    let (def, diagnostics) = parse_checked(&mut scanner);
+    let cst = scanner.finish_cst().expect("scanner builds a cst");
+    return (cst, def, diagnostics);
+}
 
-    let mut lookahead = scanner.next_token()?;
-    while lookahead != Token::EOF {
-        match lookahead {
-            //TODO parse also message, enum
+fn parse_checked(scanner: &mut Scanner) -> (ProtoDef, Vec<ProtoParseError>) {
+    let def = parse_def(scanner);
+    let mut diagnostics = scanner.take_diagnostics();
+
+    check_features(&def, &mut diagnostics);
+    check_extensions(&def, &mut diagnostics);
+    //the checks run after parsing, their errors go between the parse errors
+    diagnostics.sort_by_key(|e| e.span().map_or(usize::MAX, |s| s.start));
+    return (def, diagnostics);
+}
+
+fn parse_def(scanner: &mut Scanner) -> ProtoDef {
+    //like protoc, a file without syntax statement is proto2
+    let mut def = ProtoDef::new(Syntax::V2);
+    let mut first = true;
+    parse_statements(scanner, Token::EOF, |token, scanner| {
+        let first_statement = first;
+        first = false;
+        match token {
+            Token::Syntax if first_statement => {
+                def.comments = leading_comments(scanner);
+                def.syntax = parse_syntax(scanner)?;
+                def.comments.trailing = trailing_comment(scanner);
+            }
+            Token::Edition if first_statement => {
+                def.comments = leading_comments(scanner);
+                def.syntax = parse_edition(scanner)?;
+                def.comments.trailing = trailing_comment(scanner);
+            }
             Token::Import => {
-                let imp = parse_import(&mut scanner)?;
+                let imp = parse_import(scanner)?;
                 def.add_import(imp);
             }
             Token::Package => {
-                let pckg = parse_package(&mut scanner)?;
+                let pckg = parse_package(scanner)?;
                 def.add_package(pckg);
             }
             Token::Option => {
-                let opt = parse_option(&mut scanner)?;
+                let opt = parse_option(scanner)?;
                 def.add_option(opt);
             }
             Token::Service => {
-                let service = parse_service(&mut scanner)?;
+                let service = parse_service(scanner)?;
                 def.add_service(service);
             }
             Token::Message => {
-                let message = parse_message(&mut scanner)?;
+                let message = parse_message(scanner, def.syntax)?;
                 def.add_message(message);
             }
-            Token::Semicolon => {} //simply ignore that
+            Token::Enum => {
+                let enum_def = parse_enum(scanner, def.syntax)?;
+                def.add_enum(enum_def);
+            }
+            Token::Extend => {
+                let mut groups = Vec::new();
+                let extend = parse_extend(scanner, def.syntax, &mut groups)?;
+                def.add_extend(extend);
+                for group in groups {
+                    def.add_message(group);
+                }
+            }
             token => {
-                return err(&format!("unexpected token {:?}", token));
+                return unexpected(
+                    &token,
+                    &[
+                        "`import`",
+                        "`package`",
+                        "`option`",
+                        "`message`",
+                        "`enum`",
+                        "`service`",
+                        "`extend`",
+                    ],
+                );
             }
         }
+        return Ok(());
+    });
+    return def;
+}
 
-        lookahead = scanner.next_token()?;
+//parses the statements of a block up to term_token, which is } or EOF for
+//the top level, and skips empty statements. A statement that fails is
+//reported and skipped, parsing continues with the next statement.
+//Statements start their syntax tree node, which is closed here.
+fn parse_statements(
+    scanner: &mut Scanner,
+    term_token: Token,
+    mut statement: impl FnMut(Token, &mut Scanner) -> Result<(), ProtoParseError>,
+) {
+    let depth = scanner.depth();
+    let node_depth = scanner.node_depth();
+    loop {
+        let result = match scanner.next_token() {
+            Ok(token) if token == term_token => return,
+            Ok(Token::EOF) => unexpected(&Token::EOF, &[&term_token.to_string()]),
+            Ok(Token::Semicolon) => Ok(()),
+            Ok(token) => {
+                let result = statement(token, scanner);
+                if result.is_err() && scanner.node_depth() == node_depth {
+                    scanner.start_node(NodeKind::Error);
+                }
+                result
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            scanner.report(e);
+            //the skipped tokens are part of the failed statement
+            scanner.finish_nodes(node_depth + 1);
+            let skipped = skip_statement(scanner, depth);
+            scanner.finish_nodes(node_depth);
+            match skipped {
+                Skipped::Statement => {}
+                Skipped::Block if term_token == Token::RCurly => return,
+                Skipped::Block => {}
+                Skipped::Eof => return,
+            }
+        }
+        scanner.finish_nodes(node_depth);
     }
-    return Ok(def);
+}
+
+enum Skipped {
+    //up to the ; or the } of the failed statement
+    Statement,
+    //up to the } of the enclosing block
+    Block,
+    Eof,
+}
+
+//skips the rest of a failed statement, starting with the token where it
+//failed. depth is the brace depth of the block containing the statement,
+//nested blocks and aggregate values are skipped as a whole.
+fn skip_statement(scanner: &mut Scanner, depth: usize) -> Skipped {
+    let mut token = scanner.token().cloned();
+    loop {
+        match token {
+            Some(Token::Semicolon) if scanner.depth() == depth => return Skipped::Statement,
+            Some(Token::RCurly) if scanner.depth() < depth => return Skipped::Block,
+            Some(Token::RCurly) if scanner.depth() == depth => return Skipped::Statement,
+            Some(Token::EOF) => return Skipped::Eof,
+            _ => {}
+        }
+        token = match scanner.next_token() {
+            Ok(next) => Some(next),
+            Err(e) => {
+                scanner.report(e);
+                None
+            }
+        };
+    }
+}
+
+//leading and detached comments of the first token of a declaration
+fn leading_comments(scanner: &Scanner) -> Comments {
+    return Comments {
+        leading: scanner.leading_comment().map(String::from),
+        trailing: None,
+        detached: scanner.detached_comments().to_vec(),
+    };
+}
+
+//trailing comment of the last token of a declaration, the ; or the opening {
+fn trailing_comment(scanner: &Scanner) -> Option<String> {
+    return scanner.trailing_comment().map(String::from);
 }
 
 pub fn parse_from_file(file: &Path) -> Result<ProtoDef, ProtoParseError> {
     let mut buffer = String::new();
-    let open_result = File::open(file);
-    if open_result.is_err() {
-        return err("proto file cannot be opened");
-    }
-    let read_result = open_result.unwrap().read_to_string(&mut buffer);
-    if read_result.is_err() {
-        return err(&format!("cannot read file: {:?}", file));
+    let read_result = File::open(file).and_then(|mut f| f.read_to_string(&mut buffer));
+    if let Err(e) = read_result {
+        return Err(ProtoParseError::Io {
+            path: file.to_path_buf(),
+            kind: e.kind(),
+        });
     }
-    //TODO handle error and return parse_error
     return parse(&buffer);
 }
 
+//parses the statement after the syntax keyword
 fn parse_syntax(scanner: &mut Scanner) -> Result<Syntax, ProtoParseError> {
-    expect(scanner, Token::Syntax)?;
+    scanner.start_node(NodeKind::Syntax);
+    expect(scanner, Token::Eq)?;
+    let next = scanner.next_token()?;
+    let syntax = match next {
+        Token::StrLit(ref s) if s == "proto2" => Syntax::V2,
+        Token::StrLit(ref s) if s == "proto3" => Syntax::V3,
+        _ => return err(&format!("unknown syntax {}", next)),
+    };
+    expect(scanner, Token::Semicolon)?;
+    return Ok(syntax);
+}
+
+//parses the statement after the edition keyword
+fn parse_edition(scanner: &mut Scanner) -> Result<Syntax, ProtoParseError> {
+    scanner.start_node(NodeKind::Edition);
     expect(scanner, Token::Eq)?;
-    expect(scanner, Token::StrLit("proto3".to_string()))?;
+    let next = scanner.next_token()?;
+    let edition = match next {
+        Token::StrLit(ref s) if s == "2023" => Edition::Edition2023,
+        Token::StrLit(ref s) if s == "2024" => Edition::Edition2024,
+        _ => return err(&format!("unsupported edition {}", next)),
+    };
     expect(scanner, Token::Semicolon)?;
-    return Ok(Syntax::V3);
+    return Ok(Syntax::Edition(edition));
 }
 
 fn parse_import(scanner: &mut Scanner) -> Result<Import, ProtoParseError> {
+    scanner.start_node(NodeKind::Import);
+    let start = scanner.span();
+    let mut comments = leading_comments(scanner);
     let mut next = scanner.next_token()?;
 
     let mut import_type = ImportType::Default;
@@ -83,252 +265,1376 @@ fn parse_import(scanner: &mut Scanner) -> Result<Import, ProtoParseError> {
 
     let name = match next {
         Token::StrLit(s) => s,
-        _ => return err("string literal expected in import"),
+        _ => return unexpected(&next, &["string literal"]),
     };
 
     expect(scanner, Token::Semicolon)?;
+    comments.trailing = trailing_comment(scanner);
 
-    return Ok(Import { import_type, name });
+    return Ok(Import {
+        import_type,
+        name,
+        span: start.to(scanner.span()),
+        comments,
+    });
 }
 
 fn parse_package(scanner: &mut Scanner) -> Result<Package, ProtoParseError> {
+    scanner.start_node(NodeKind::Package);
+    let start = scanner.span();
+    let mut comments = leading_comments(scanner);
     let full_ident = parse_full_ident(scanner, Token::Semicolon)?;
+    comments.trailing = trailing_comment(scanner);
     return Ok(Package {
-        full_ident: full_ident,
+        full_ident,
+        span: start.to(scanner.span()),
+        comments,
     });
 }
 
-//term_token is consumed
+//ident { "." ident }, term_token is consumed
 fn parse_full_ident(
     scanner: &mut Scanner,
     term_token: Token,
 ) -> Result<FullIdent, ProtoParseError> {
-    let mut idents = Vec::new();
-    let mut rparen = false;
-
-    let mut first_ident = scanner.next_token()?;
-    match first_ident {
-        Token::Ident(s) => idents.push(s),
-        Token::LParen => {
-            first_ident = scanner.next_token()?;
-            rparen = true;
-            match first_ident {
-                Token::Ident(s) => idents.push(s),
-                _ => return err("FullIdent: identifier expected"),
-            }
+    let mut idents = vec![expect_ident(scanner)?];
+    let start = scanner.span();
+    let mut next = scanner.next_token()?;
+    while next != term_token {
+        if next != Token::Dot {
+            return unexpected(&next, &["`.`", &term_token.to_string()]);
         }
-        _ => return err("FullIdent: identifier expected"),
-    }
-
-    let mut ident_term = term_token.clone();
-    if rparen {
-        ident_term = Token::RParen;
+        idents.push(expect_ident(scanner)?);
+        next = scanner.next_token()?;
     }
+    return Ok(FullIdent {
+        idents,
+        span: start.to(scanner.prev_span()),
+    });
+}
 
-    let mut next = scanner.next_token()?;
+//part { "." part }, part = ident | "(" ["."] fullIdent ")", term_token is
+//consumed. Also returns the span of the first token of the name.
+fn parse_option_name(
+    scanner: &mut Scanner,
+    term_token: Token,
+) -> Result<(Vec<OptionNamePart>, Span), ProtoParseError> {
+    let mut parts = Vec::new();
+    let SpannedToken {
+        token: mut next,
+        span: start,
+    } = scanner.next_spanned_token()?;
     loop {
-        if next == ident_term {
-            break;
+        if next == Token::LParen {
+            next = scanner.next_token()?;
+            let fully_qualified = next == Token::Dot;
+            if fully_qualified {
+                next = scanner.next_token()?;
+            }
+            let ident_start = scanner.span();
+            let mut idents = vec![keyword_ident(next)?];
+            next = scanner.next_token()?;
+            while next == Token::Dot {
+                idents.push(expect_ident(scanner)?);
+                next = scanner.next_token()?;
+            }
+            if next != Token::RParen {
+                return unexpected(&next, &["`.`", "`)`"]);
+            }
+            parts.push(OptionNamePart {
+                name: FullIdent {
+                    idents,
+                    span: ident_start.to(scanner.prev_span()),
+                },
+                extension: true,
+                fully_qualified,
+            });
+        } else {
+            match as_ident(&next) {
+                Some(name) => parts.push(OptionNamePart {
+                    name: FullIdent {
+                        idents: vec![name],
+                        span: scanner.span(),
+                    },
+                    extension: false,
+                    fully_qualified: false,
+                }),
+                None => return unexpected(&next, &["identifier", "`(`"]),
+            }
         }
 
-        if next != Token::Dot {
-            return err("FullIdent: . expected");
-        }
         next = scanner.next_token()?;
-        match next {
-            Token::Ident(s) => idents.push(s),
-            _ => return err("FullIdent: identifier expected"),
+        if next == term_token {
+            return Ok((parts, start));
+        }
+        if next != Token::Dot {
+            return unexpected(&next, &["`.`", &term_token.to_string()]);
         }
         next = scanner.next_token()?;
     }
-
-    if rparen {
-        expect(scanner, term_token)?;
-    }
-
-    return Ok(FullIdent { idents: idents });
 }
 
 fn parse_option(scanner: &mut Scanner) -> Result<ProtoOption, ProtoParseError> {
-    let ident = parse_full_ident(scanner, Token::Eq)?;
+    scanner.start_node(NodeKind::Option);
+    let start = scanner.span();
+    let mut comments = leading_comments(scanner);
+    let (name, _) = parse_option_name(scanner, Token::Eq)?;
     let constant = parse_constant(scanner)?;
+    comments.trailing = trailing_comment(scanner);
 
     return Ok(ProtoOption {
-        full_ident: ident,
-        constant: constant,
+        name,
+        constant,
+        span: start.to(scanner.span()),
+        comments,
     });
 }
 
 //also parses the semicolon!
 fn parse_constant(scanner: &mut Scanner) -> Result<ConstantValue, ProtoParseError> {
+    let (constant, next) = parse_constant_value(scanner)?;
+    if next != Token::Semicolon {
+        return unexpected(&next, &[&Token::Semicolon.to_string()]);
+    }
+    return Ok(constant);
+}
+
+//returns the constant and the token that followed it
+fn parse_constant_value(scanner: &mut Scanner) -> Result<(ConstantValue, Token), ProtoParseError> {
     let next = scanner.next_token()?;
-    match next {
-        Token::StrLit(s) => {
-            expect(scanner, Token::Semicolon)?;
-            return Ok(ConstantValue::StringValue(s));
-        }
-        Token::DecimalLit(d) => {
-            expect(scanner, Token::Semicolon)?;
-            return Ok(ConstantValue::NumberValue(d as f32));
-        }
-        Token::BoolLit(b) => {
-            expect(scanner, Token::Semicolon)?;
-            return Ok(ConstantValue::BoolValue(b));
-        }
-        Token::Ident(n) => {
-            let ident_next = scanner.next_token()?;
-            if ident_next == Token::Semicolon {
-                return Ok(ConstantValue::IdentValue(FullIdent::new(vec![n])));
-            } else if ident_next == Token::Dot {
-                let mut rest_ident = parse_full_ident(scanner, Token::Semicolon)?;
-                rest_ident.insert(0, n);
-                return Ok(ConstantValue::IdentValue(rest_ident));
-            }
-            return err("unexepected token in constant identifier");
-        }
+    return parse_constant_from(next, scanner);
+}
+
+//like parse_constant_value, with the first token of the constant already read
+fn parse_constant_from(
+    next: Token,
+    scanner: &mut Scanner,
+) -> Result<(ConstantValue, Token), ProtoParseError> {
+    let constant = match next {
+        Token::LCurly => parse_aggregate(scanner, Token::RCurly)?,
+        Token::StrLit(s) => ConstantValue::StringValue(s),
+        Token::BytesLit(b) => ConstantValue::BytesValue(b),
+        Token::IntLit(i) => ConstantValue::IntValue(i as i128),
+        Token::FloatLit(f) => ConstantValue::FloatValue(f),
+        Token::BoolLit(b) => ConstantValue::BoolValue(b),
+        Token::Ident(ref n) if n == "inf" => ConstantValue::FloatValue(f64::INFINITY),
+        Token::Ident(ref n) if n == "nan" => ConstantValue::FloatValue(f64::NAN),
         Token::Plus => {
             let num = scanner.next_token()?;
             match num {
-                Token::DecimalLit(d) => return Ok(ConstantValue::NumberValue(d as f32)),
-                _ => err("unexpected token after +"),
+                Token::IntLit(i) => ConstantValue::IntValue(i as i128),
+                Token::FloatLit(f) => ConstantValue::FloatValue(f),
+                Token::Ident(ref n) if n == "inf" => ConstantValue::FloatValue(f64::INFINITY),
+                Token::Ident(ref n) if n == "nan" => ConstantValue::FloatValue(f64::NAN),
+                _ => return unexpected(&num, &["number"]),
             }
         }
         Token::Minus => {
             let num = scanner.next_token()?;
             match num {
-                Token::DecimalLit(d) => return Ok(ConstantValue::NumberValue(-(d as f32))),
-                _ => err("unexpected token after -"),
+                Token::IntLit(i) => ConstantValue::IntValue(-(i as i128)),
+                Token::FloatLit(f) => ConstantValue::FloatValue(-f),
+                Token::Ident(ref n) if n == "inf" => ConstantValue::FloatValue(f64::NEG_INFINITY),
+                Token::Ident(ref n) if n == "nan" => ConstantValue::FloatValue(-f64::NAN),
+                _ => return unexpected(&num, &["number"]),
+            }
+        }
+        token => {
+            //enum values may be named like a keyword
+            let Some(name) = as_ident(&token) else {
+                return unexpected(&token, &["constant"]);
+            };
+            let start = scanner.span();
+            let mut idents = vec![name];
+            let mut ident_next = scanner.next_token()?;
+            while ident_next == Token::Dot {
+                idents.push(expect_ident(scanner)?);
+                ident_next = scanner.next_token()?;
+            }
+            let full_ident = FullIdent {
+                idents,
+                span: start.to(scanner.prev_span()),
+            };
+            return Ok((ConstantValue::IdentValue(full_ident), ident_next));
+        }
+    };
+    return Ok((constant, scanner.next_token()?));
+}
+
+//protobuf text format message literal after the opening { or <, term_token is consumed
+fn parse_aggregate(
+    scanner: &mut Scanner,
+    term_token: Token,
+) -> Result<ConstantValue, ProtoParseError> {
+    let mut fields = Vec::new();
+    let mut next = scanner.next_token()?;
+    while next != term_token {
+        let start = scanner.span();
+        let mut extension = false;
+        let name = if next == Token::LBracket {
+            extension = true;
+            parse_full_ident(scanner, Token::RBracket)?
+        } else {
+            FullIdent {
+                idents: vec![keyword_ident(next)?],
+                span: start,
+            }
+        };
+
+        next = scanner.next_token()?;
+        let value;
+        if next == Token::Colon {
+            let value_start = scanner.next_token()?;
+            (value, next) = parse_aggregate_value(value_start, scanner)?;
+        } else if next == Token::LCurly || next == Token::Lt {
+            //the colon is optional for message values
+            (value, next) = parse_aggregate_value(next, scanner)?;
+        } else {
+            return unexpected(&next, &["`:`", "`{`", "`<`"]);
+        }
+
+        fields.push(AggregateField {
+            name,
+            extension,
+            value,
+            span: start.to(scanner.prev_span()),
+        });
+
+        if next == Token::Semicolon || next == Token::Comma {
+            next = scanner.next_token()?;
+        }
+    }
+    return Ok(ConstantValue::AggregateValue(fields));
+}
+
+//returns the value and the token that followed it
+fn parse_aggregate_value(
+    token: Token,
+    scanner: &mut Scanner,
+) -> Result<(ConstantValue, Token), ProtoParseError> {
+    match token {
+        Token::Lt => {
+            let aggregate = parse_aggregate(scanner, Token::Gt)?;
+            return Ok((aggregate, scanner.next_token()?));
+        }
+        Token::LBracket => {
+            let mut values = Vec::new();
+            let mut next = scanner.next_token()?;
+            while next != Token::RBracket {
+                let value;
+                (value, next) = parse_aggregate_value(next, scanner)?;
+                values.push(value);
+                match next {
+                    Token::Comma => next = scanner.next_token()?,
+                    Token::RBracket => {}
+                    _ => return unexpected(&next, &["`,`", "`]`"]),
+                }
             }
+            return Ok((ConstantValue::ListValue(values), scanner.next_token()?));
         }
-        _ => err("unexpected token in constant expression"),
+        _ => parse_constant_from(token, scanner),
     }
+}
 
-    // TODO parse +, - num values
+//optional [options] followed by term_token, which is consumed
+fn parse_trailing_options(
+    scanner: &mut Scanner,
+    term_token: Token,
+) -> Result<Vec<ProtoOption>, ProtoParseError> {
+    let mut options = Vec::new();
+    let mut next = scanner.next_token()?;
+    if next == Token::LBracket {
+        options = parse_option_list(scanner)?;
+        next = scanner.next_token()?;
+        if next != term_token {
+            return unexpected(&next, &[&term_token.to_string()]);
+        }
+    } else if next != term_token {
+        return unexpected(&next, &["`[`", &term_token.to_string()]);
+    }
+    return Ok(options);
 }
 
-fn parse_service(scanner: &mut Scanner) -> Result<Service, ProtoParseError> {
+//parses the options after the opening [, the closing ] is consumed
+fn parse_option_list(scanner: &mut Scanner) -> Result<Vec<ProtoOption>, ProtoParseError> {
+    let mut options = Vec::new();
+    loop {
+        let (name, start) = parse_option_name(scanner, Token::Eq)?;
+        let (constant, next) = parse_constant_value(scanner)?;
+        options.push(ProtoOption {
+            name,
+            constant,
+            span: start.to(scanner.prev_span()),
+            comments: Comments::default(),
+        });
+        match next {
+            Token::Comma => continue,
+            Token::RBracket => return Ok(options),
+            _ => return unexpected(&next, &["`,`", "`]`"]),
+        }
+    }
+}
+
+fn parse_enum(scanner: &mut Scanner, syntax: Syntax) -> Result<Enum, ProtoParseError> {
+    scanner.start_node(NodeKind::Enum);
+    let start = scanner.span();
+    let mut comments = leading_comments(scanner);
     let name = expect_ident(scanner)?;
     expect(scanner, Token::LCurly)?;
+    comments.trailing = trailing_comment(scanner);
+    let mut values = Vec::new();
+    let mut reserved = Vec::new();
+    let mut options = Vec::new();
+    parse_statements(scanner, Token::RCurly, |peeked, scanner| {
+        match peeked {
+            Token::Option => {
+                let opt = parse_option(scanner)?;
+                options.push(opt);
+            }
+            Token::Reserved => {
+                let res = parse_reserved(scanner, i32::MIN as i64, i32::MAX as i64, syntax)?;
+                reserved.push(res);
+            }
+            token => {
+                let Some(value_name) = as_ident(&token) else {
+                    return unexpected(&token, &["enum value", "`option`", "`reserved`", "`}`"]);
+                };
+                let value = parse_enum_value(value_name, scanner)?;
+                values.push(value);
+            }
+        }
+        return Ok(());
+    });
 
-    let mut rpcs = Vec::new();
+    let span = start.to(scanner.span());
+    for value in values.iter() {
+        let number = value.number as i64;
+        if let Err(e) = check_reserved(&name, &reserved, &value.name, number, value.span) {
+            scanner.report(e);
+        }
+    }
+
+    return Ok(Enum {
+        name,
+        values,
+        reserved,
+        options,
+        span,
+        comments,
+    });
+}
+
+fn parse_enum_value(name: String, scanner: &mut Scanner) -> Result<EnumValue, ProtoParseError> {
+    scanner.start_node(NodeKind::EnumValue);
+    let start = scanner.span();
+    let mut comments = leading_comments(scanner);
+    expect(scanner, Token::Eq)?;
+    let next = scanner.next_token()?;
+    let n = parse_int_lit(next, scanner)?;
+    if n < i32::MIN as i64 || n > i32::MAX as i64 {
+        return err(&format!("enum value {} out of int32 range", name));
+    }
+    let number = n as i32;
+
+    let options = parse_trailing_options(scanner, Token::Semicolon)?;
+    comments.trailing = trailing_comment(scanner);
+
+    return Ok(EnumValue {
+        name,
+        number,
+        options,
+        span: start.to(scanner.span()),
+        comments,
+    });
+}
+
+//[-]intLit
+fn parse_int_lit(token: Token, scanner: &mut Scanner) -> Result<i64, ProtoParseError> {
+    let mut next = token;
+    let mut negative = false;
+    if next == Token::Minus {
+        negative = true;
+        next = scanner.next_token()?;
+    }
+    match next {
+        Token::IntLit(i) => {
+            let mut n = i as i128;
+            if negative {
+                n = -n;
+            }
+            match i64::try_from(n) {
+                Ok(n) => Ok(n),
+                Err(_) => err(&format!("integer {} out of range", n)),
+            }
+        }
+        _ => unexpected(&next, &["integer"]),
+    }
+}
+
+//parses the statement after the reserved keyword, including the semicolon.
+//min and max are the allowed numbers, max is also the value of the max keyword.
+//Reserved names are string literals, in editions they are identifiers.
+fn parse_reserved(
+    scanner: &mut Scanner,
+    min: i64,
+    max: i64,
+    syntax: Syntax,
+) -> Result<Reserved, ProtoParseError> {
+    scanner.start_node(NodeKind::Reserved);
+    let start = scanner.span();
+    let mut comments = leading_comments(scanner);
     let mut next = scanner.next_token()?;
-    while next == Token::Rpc {
-        let rpc_def = parse_rpc(scanner)?;
-        rpcs.push(rpc_def);
+
+    let editions = matches!(syntax, Syntax::Edition(_));
+    if matches!(next, Token::StrLit(_) | Token::Ident(_)) {
+        let mut names = Vec::new();
+        loop {
+            match next {
+                Token::StrLit(s) if !editions => names.push(s),
+                Token::Ident(s) if editions => names.push(s),
+                _ if editions => {
+                    return unexpected(&next, &["identifier"]);
+                }
+                _ => {
+                    return unexpected(&next, &["string literal"]);
+                }
+            }
+            next = scanner.next_token()?;
+            match next {
+                Token::Comma => next = scanner.next_token()?,
+                Token::Semicolon => {
+                    comments.trailing = trailing_comment(scanner);
+                    return Ok(Reserved {
+                        ranges: Vec::new(),
+                        names,
+                        span: start.to(scanner.span()),
+                        comments,
+                    });
+                }
+                _ => return unexpected(&next, &["`,`", "`;`"]),
+            }
+        }
+    }
+
+    let (ranges, next) = parse_ranges(next, scanner, min, max)?;
+    if next != Token::Semicolon {
+        return unexpected(&next, &["`,`", "`;`"]);
+    }
+    comments.trailing = trailing_comment(scanner);
+    let ranges = ranges
+        .into_iter()
+        .map(|(start, end, span)| ReservedRange { start, end, span })
+        .collect();
+    return Ok(Reserved {
+        ranges,
+        names: Vec::new(),
+        span: start.to(scanner.span()),
+        comments,
+    });
+}
+
+//range { "," range }, range = intLit [ "to" ( intLit | "max" ) ].
+//Returns the inclusive ranges with their spans and the token that followed them.
+fn parse_ranges(
+    token: Token,
+    scanner: &mut Scanner,
+    min: i64,
+    max: i64,
+) -> Result<(Vec<Range>, Token), ProtoParseError> {
+    let mut next = token;
+    let mut ranges = Vec::new();
+    loop {
+        let range_start = scanner.span();
+        let start = parse_int_lit(next, scanner)?;
+        let mut end = start;
+        next = scanner.next_token()?;
+        if next == Token::To {
+            next = scanner.next_token()?;
+            if next == Token::Max {
+                end = max;
+            } else {
+                end = parse_int_lit(next, scanner)?;
+            }
+            next = scanner.next_token()?;
+        }
+        let span = range_start.to(scanner.prev_span());
+
+        if start < min || end > max {
+            return err(&format!(
+                "range {} to {} out of bounds [{}, {}]",
+                start, end, min, max
+            ));
+        }
+        if start > end {
+            return err(&format!("range {} to {} is empty", start, end));
+        }
+        ranges.push((start as i32, end as i32, span));
+
+        if next != Token::Comma {
+            return Ok((ranges, next));
+        }
+        next = scanner.next_token()?;
+    }
+}
+
+//parses the statement after the extensions keyword, including the semicolon
+fn parse_extensions(scanner: &mut Scanner) -> Result<Extensions, ProtoParseError> {
+    scanner.start_node(NodeKind::Extensions);
+    let start = scanner.span();
+    let mut comments = leading_comments(scanner);
+    let next = scanner.next_token()?;
+    let (ranges, mut next) = parse_ranges(next, scanner, 1, MAX_FIELD_NUMBER)?;
+
+    let mut options = Vec::new();
+    if next == Token::LBracket {
+        options = parse_option_list(scanner)?;
         next = scanner.next_token()?;
     }
+    if next != Token::Semicolon {
+        return unexpected(&next, &["`,`", "`[`", "`;`"]);
+    }
+    comments.trailing = trailing_comment(scanner);
+
+    let ranges = ranges
+        .into_iter()
+        .map(|(start, end, span)| ExtensionRange { start, end, span })
+        .collect();
+    return Ok(Extensions {
+        ranges,
+        options,
+        span: start.to(scanner.span()),
+        comments,
+    });
+}
+
+//extend messageType { fields }, after the extend keyword. Group messages are
+//added to groups.
+fn parse_extend(
+    scanner: &mut Scanner,
+    syntax: Syntax,
+    groups: &mut Vec<Message>,
+) -> Result<Extend, ProtoParseError> {
+    scanner.start_node(NodeKind::Extend);
+    let start = scanner.span();
+    let mut comments = leading_comments(scanner);
+    let next = scanner.next_token()?;
+    let (extendee, next) = parse_type_reference(next, scanner)?;
+    let (extendee, fully_qualified) = match extendee {
+        Type::Reference {
+            full_ident,
+            fully_qualified,
+        } => (full_ident, fully_qualified),
+        _ => return err("extend: message type expected"),
+    };
+    if next != Token::LCurly {
+        return unexpected(&next, &[&Token::LCurly.to_string()]);
+    }
+    comments.trailing = trailing_comment(scanner);
+
+    let mut fields = Vec::new();
+    parse_statements(scanner, Token::RCurly, |peeked, scanner| {
+        let field = parse_field(peeked, scanner, syntax, groups)?;
+        if matches!(field.field_type, Type::Map { .. }) {
+            return err_at(field.span, "extend: extension fields cannot be maps");
+        }
+        check_label(&extendee.idents.join("."), &field, syntax)?;
+        fields.push(field);
+        return Ok(());
+    });
+
+    return Ok(Extend {
+        extendee,
+        fully_qualified,
+        fields,
+        span: start.to(scanner.span()),
+        comments,
+    });
+}
+
+//extension fields must be in an extension range of the extended message.
+//This is only checked if the extended message is declared in the same file.
+fn check_extensions(def: &ProtoDef, diagnostics: &mut Vec<ProtoParseError>) {
+    let package = match def.packages.first() {
+        Some(p) => p.full_ident.idents.clone(),
+        None => Vec::new(),
+    };
+    let mut messages = Vec::new();
+    collect_messages(&def.messages, &package, &mut messages);
+
+    for extend in def.extends.iter() {
+        check_extend(extend, &package, &messages, diagnostics);
+    }
+    for (scope, message) in messages.iter() {
+        for extend in message.extends.iter() {
+            check_extend(extend, scope, &messages, diagnostics);
+        }
+    }
+}
 
-    if next != Token::RCurly {
-        return err("Unexpected token, expected }");
+fn collect_messages<'a>(
+    messages: &'a [Message],
+    scope: &[String],
+    all: &mut Vec<(Vec<String>, &'a Message)>,
+) {
+    for message in messages.iter() {
+        let mut name = scope.to_vec();
+        name.push(message.name.clone());
+        collect_messages(&message.nested_messages, &name, all);
+        all.push((name, message));
     }
+}
+
+fn check_extend(
+    extend: &Extend,
+    scope: &[String],
+    messages: &[(Vec<String>, &Message)],
+    diagnostics: &mut Vec<ProtoParseError>,
+) {
+    let find = |name: &[String]| messages.iter().find(|(n, _)| n == name).map(|(_, m)| *m);
 
-    return Ok(Service { name: name, rpcs });
+    let mut extendee = None;
+    if extend.fully_qualified {
+        extendee = find(&extend.extendee.idents);
+    } else {
+        //search from the innermost scope outwards
+        for i in (0..=scope.len()).rev() {
+            let mut candidate = scope[..i].to_vec();
+            candidate.extend(extend.extendee.idents.iter().cloned());
+            extendee = find(&candidate);
+            if extendee.is_some() {
+                break;
+            }
+        }
+    }
+
+    let Some(message) = extendee else {
+        return;
+    };
+    for field in extend.fields.iter() {
+        if !message
+            .extensions
+            .iter()
+            .any(|e| e.contains(field.field_number as i64))
+        {
+            diagnostics.push(ProtoParseError::Semantic {
+                message: format!(
+                    "extension {} = {} is not in an extension range of {}",
+                    field.name, field.field_number, message.name
+                ),
+                span: Some(field.span),
+            });
+        }
+    }
+}
+
+//span is the span of the field or enum value, the error points to it and
+//names the line of the reserved statement
+fn check_reserved(
+    scope: &str,
+    reserved: &[Reserved],
+    name: &str,
+    number: i64,
+    span: Span,
+) -> Result<(), ProtoParseError> {
+    for res in reserved.iter() {
+        let line = res.span.line;
+        if res.contains_name(name) {
+            return err_at(
+                span,
+                &format!("{}: name {} is reserved on line {}", scope, name, line),
+            );
+        }
+        if res.contains_number(number) {
+            return err_at(
+                span,
+                &format!(
+                    "{}: number {} of {} is reserved on line {}",
+                    scope, number, name, line
+                ),
+            );
+        }
+    }
+    return Ok(());
+}
+
+//features options are only allowed in editions and must be known features
+fn check_features(def: &ProtoDef, diagnostics: &mut Vec<ProtoParseError>) {
+    let editions = matches!(def.syntax, Syntax::Edition(_));
+    let mut check = |options: &[ProtoOption]| check_feature_options(options, editions, diagnostics);
+    check(&def.options);
+    for message in def.messages.iter() {
+        check_message_features(message, &mut check);
+    }
+    for enum_def in def.enums.iter() {
+        check_enum_features(enum_def, &mut check);
+    }
+    for extend in def.extends.iter() {
+        for field in extend.fields.iter() {
+            check(&field.options);
+        }
+    }
+    for service in def.services.iter() {
+        check(&service.options);
+        for rpc in service.rpcs.iter() {
+            check(&rpc.options);
+        }
+    }
+}
+
+fn check_message_features(message: &Message, check: &mut impl FnMut(&[ProtoOption])) {
+    check(&message.options);
+    for field in message.fields.iter() {
+        check(&field.options);
+    }
+    for oneof in message.oneofs.iter() {
+        check(&oneof.options);
+        for field in oneof.fields.iter() {
+            check(&field.options);
+        }
+    }
+    for nested in message.nested_messages.iter() {
+        check_message_features(nested, check);
+    }
+    for enum_def in message.nested_enums.iter() {
+        check_enum_features(enum_def, check);
+    }
+    for extend in message.extends.iter() {
+        for field in extend.fields.iter() {
+            check(&field.options);
+        }
+    }
+    for extensions in message.extensions.iter() {
+        check(&extensions.options);
+    }
+}
+
+fn check_enum_features(enum_def: &Enum, check: &mut impl FnMut(&[ProtoOption])) {
+    check(&enum_def.options);
+    for value in enum_def.values.iter() {
+        check(&value.options);
+    }
+}
+
+fn check_feature_options(
+    options: &[ProtoOption],
+    editions: bool,
+    diagnostics: &mut Vec<ProtoParseError>,
+) {
+    for option in options.iter().filter(|o| is_feature_option(o)) {
+        if !editions {
+            diagnostics.push(ProtoParseError::Semantic {
+                message: "features are only allowed in editions".to_string(),
+                span: Some(option.span),
+            });
+        } else if let Err(e) = FeatureSet::from_options(std::slice::from_ref(option)) {
+            diagnostics.push(e.or_span(option.span));
+        }
+    }
+}
+
+fn parse_service(scanner: &mut Scanner) -> Result<Service, ProtoParseError> {
+    scanner.start_node(NodeKind::Service);
+    let start = scanner.span();
+    let mut comments = leading_comments(scanner);
+    let name = expect_ident(scanner)?;
+    expect(scanner, Token::LCurly)?;
+    comments.trailing = trailing_comment(scanner);
+
+    let mut rpcs = Vec::new();
+    let mut options = Vec::new();
+    parse_statements(scanner, Token::RCurly, |next, scanner| {
+        match next {
+            Token::Option => {
+                let opt = parse_option(scanner)?;
+                options.push(opt);
+            }
+            Token::Rpc => {
+                let rpc_def = parse_rpc(scanner)?;
+                rpcs.push(rpc_def);
+            }
+            token => return unexpected(&token, &["`rpc`", "`option`", "`}`"]),
+        }
+        return Ok(());
+    });
+
+    return Ok(Service {
+        name,
+        rpcs,
+        options,
+        span: start.to(scanner.span()),
+        comments,
+    });
 }
 
 fn parse_rpc(scanner: &mut Scanner) -> Result<Rpc, ProtoParseError> {
+    scanner.start_node(NodeKind::Rpc);
+    let start = scanner.span();
+    let mut comments = leading_comments(scanner);
     let name = expect_ident(scanner)?;
 
     expect(scanner, Token::LParen)?;
-    let req_message_type = parse_full_ident(scanner, Token::RParen)?;
+    let (client_streaming, request_type, request_fully_qualified) = parse_rpc_type(scanner)?;
     expect(scanner, Token::Returns)?;
 
     expect(scanner, Token::LParen)?;
-    let resp_message_type = parse_full_ident(scanner, Token::RParen)?;
+    let (server_streaming, response_type, response_fully_qualified) = parse_rpc_type(scanner)?;
 
+    let mut options = Vec::new();
     let next = scanner.next_token()?;
+    comments.trailing = trailing_comment(scanner);
     if next == Token::LCurly {
-        expect(scanner, Token::RCurly)?;
+        parse_statements(scanner, Token::RCurly, |body_next, scanner| {
+            match body_next {
+                Token::Option => {
+                    let opt = parse_option(scanner)?;
+                    options.push(opt);
+                }
+                token => return unexpected(&token, &["`option`", "`}`"]),
+            }
+            return Ok(());
+        });
     } else if next == Token::Semicolon {
         //do nothing
     } else {
-        return err("unexpected token, Semicolon or {} expected");
+        return unexpected(&next, &["`;`", "`{`"]);
     }
 
     return Ok(Rpc {
         name,
-        request_type: req_message_type,
-        response_type: resp_message_type,
+        request_type,
+        request_fully_qualified,
+        response_type,
+        response_fully_qualified,
+        client_streaming,
+        server_streaming,
+        options,
+        span: start.to(scanner.span()),
+        comments,
     });
 }
 
-fn parse_message(scanner: &mut Scanner) -> Result<Message, ProtoParseError> {
+//[stream] messageType ), the closing paren is consumed. Returns whether the
+//type is streamed, the type and whether it started with a leading dot.
+fn parse_rpc_type(scanner: &mut Scanner) -> Result<(bool, FullIdent, bool), ProtoParseError> {
+    let mut next = scanner.next_token()?;
+    let streaming = next == Token::Stream;
+    if streaming {
+        next = scanner.next_token()?;
+    }
+
+    let (message_type, next) = parse_type_reference(next, scanner)?;
+    let Type::Reference {
+        full_ident,
+        fully_qualified,
+    } = message_type
+    else {
+        unreachable!("parse_type_reference returns a reference");
+    };
+    if next != Token::RParen {
+        return unexpected(&next, &["`.`", "`)`"]);
+    }
+    return Ok((streaming, full_ident, fully_qualified));
+}
+
+fn parse_message(scanner: &mut Scanner, syntax: Syntax) -> Result<Message, ProtoParseError> {
+    scanner.start_node(NodeKind::Message);
+    let start = scanner.span();
+    let mut comments = leading_comments(scanner);
     let name = expect_ident(scanner)?;
     expect(scanner, Token::LCurly)?;
+    comments.trailing = trailing_comment(scanner);
+    return parse_message_body(name, start, comments, scanner, syntax);
+}
+
+//parses the message body after the opening {, the closing } is consumed.
+//start is the span of the first token of the message or group.
+fn parse_message_body(
+    name: String,
+    start: Span,
+    comments: Comments,
+    scanner: &mut Scanner,
+    syntax: Syntax,
+) -> Result<Message, ProtoParseError> {
     let mut fields = Vec::new();
-    let mut peeked = scanner.next_token()?;
-    while peeked != Token::RCurly {
-        let field = parse_field(peeked, scanner)?;
-        fields.push(field);
-        peeked = scanner.next_token()?;
+    let mut nested_messages = Vec::new();
+    let mut nested_enums = Vec::new();
+    let mut oneofs = Vec::new();
+    let mut reserved = Vec::new();
+    let mut options = Vec::new();
+    let mut extensions = Vec::new();
+    let mut extends = Vec::new();
+    parse_statements(scanner, Token::RCurly, |peeked, scanner| {
+        match peeked {
+            Token::Option => {
+                let opt = parse_option(scanner)?;
+                options.push(opt);
+            }
+            Token::Reserved => {
+                let res = parse_reserved(scanner, 1, MAX_FIELD_NUMBER, syntax)?;
+                reserved.push(res);
+            }
+            Token::Extensions => {
+                if syntax == Syntax::V3 {
+                    return err("extension ranges are not allowed in proto3");
+                }
+                let statement = parse_extensions(scanner)?;
+                extensions.push(statement);
+            }
+            Token::Extend => {
+                let extend = parse_extend(scanner, syntax, &mut nested_messages)?;
+                extends.push(extend);
+            }
+            Token::OneOf => {
+                let oneof = parse_oneof(scanner, syntax, &mut nested_messages)?;
+                oneofs.push(oneof);
+            }
+            Token::Message => {
+                let message = parse_message(scanner, syntax)?;
+                nested_messages.push(message);
+            }
+            Token::Enum => {
+                let enum_def = parse_enum(scanner, syntax)?;
+                nested_enums.push(enum_def);
+            }
+            _ => {
+                let field = parse_field(peeked, scanner, syntax, &mut nested_messages)?;
+                check_label(&name, &field, syntax)?;
+                fields.push(field);
+            }
+        }
+        return Ok(());
+    });
+
+    let span = start.to(scanner.span());
+    let oneof_fields = oneofs.iter().flat_map(|o| o.fields.iter());
+    for field in fields.iter().chain(oneof_fields) {
+        let number = field.field_number as i64;
+        if let Err(e) = check_reserved(&name, &reserved, &field.name, number, field.span) {
+            scanner.report(e);
+        } else if extensions.iter().any(|e| e.contains(number)) {
+            scanner.report(ProtoParseError::Semantic {
+                message: format!(
+                    "{}: field {} = {} is in an extension range",
+                    name, field.name, number
+                ),
+                span: Some(field.span),
+            });
+        }
+    }
+
+    return Ok(Message {
+        name,
+        fields,
+        nested_messages,
+        nested_enums,
+        oneofs,
+        reserved,
+        options,
+        extensions,
+        extends,
+        span,
+        comments,
+    });
+}
+
+//in proto2 all fields, except for maps and oneof fields, need a label
+fn check_label(scope: &str, field: &Field, syntax: Syntax) -> Result<(), ProtoParseError> {
+    let is_map = matches!(field.field_type, Type::Map { .. });
+    if syntax == Syntax::V2 && field.label.is_none() && !is_map {
+        return err_at(
+            field.span,
+            &format!(
+                "{}: field {} needs a label (required, optional or repeated) in proto2",
+                scope, field.name
+            ),
+        );
     }
-    return Ok(Message { name, fields });
+    return Ok(());
 }
 
-fn parse_field(peeked: Token, scanner: &mut Scanner) -> Result<Field, ProtoParseError> {
+//group messages are added to the nested messages of the enclosing message
+fn parse_oneof(
+    scanner: &mut Scanner,
+    syntax: Syntax,
+    groups: &mut Vec<Message>,
+) -> Result<OneOf, ProtoParseError> {
+    scanner.start_node(NodeKind::OneOf);
+    let start = scanner.span();
+    let mut comments = leading_comments(scanner);
+    let name = expect_ident(scanner)?;
+    expect(scanner, Token::LCurly)?;
+    comments.trailing = trailing_comment(scanner);
+    let mut fields = Vec::new();
+    let mut options = Vec::new();
+    parse_statements(scanner, Token::RCurly, |peeked, scanner| {
+        match peeked {
+            Token::Option => {
+                let opt = parse_option(scanner)?;
+                options.push(opt);
+            }
+            Token::Repeated => {
+                return err(&format!("oneof {}: fields cannot be repeated", name));
+            }
+            Token::Required | Token::Optional => {
+                return err(&format!("oneof {}: fields cannot have labels", name));
+            }
+            Token::Group => {
+                //groups in oneofs are written without label
+                scanner.start_node(NodeKind::Field);
+                let start = scanner.span();
+                let comments = leading_comments(scanner);
+                let field = parse_group(None, start, comments, scanner, syntax, groups)?;
+                fields.push(field);
+            }
+            _ => {
+                let field = parse_field(peeked, scanner, syntax, groups)?;
+                if matches!(field.field_type, Type::Map { .. }) {
+                    return err_at(
+                        field.span,
+                        &format!("oneof {}: fields cannot be maps", name),
+                    );
+                }
+                fields.push(field);
+            }
+        }
+        return Ok(());
+    });
+    return Ok(OneOf {
+        name,
+        fields,
+        options,
+        span: start.to(scanner.span()),
+        comments,
+    });
+}
+
+//if the field is a group, the group message is added to groups
+fn parse_field(
+    peeked: Token,
+    scanner: &mut Scanner,
+    syntax: Syntax,
+    groups: &mut Vec<Message>,
+) -> Result<Field, ProtoParseError> {
+    scanner.start_node(NodeKind::Field);
+    let start = scanner.span();
+    let mut comments = leading_comments(scanner);
     let mut next = peeked;
-    let mut repeated = false;
-    if next == Token::Repeated {
-        repeated = true;
+    let label = match next {
+        Token::Required => Some(FieldLabel::Required),
+        Token::Optional => Some(FieldLabel::Optional),
+        Token::Repeated => Some(FieldLabel::Repeated),
+        _ => None,
+    };
+    if label.is_some() {
         next = scanner.next_token()?;
     }
-    let field_type = is_type(next)?;
-    let name = expect_keyword_ident(scanner)?;
+    if label == Some(FieldLabel::Required) && syntax == Syntax::V3 {
+        return err("required fields are not allowed in proto3");
+    }
+    let editions = matches!(syntax, Syntax::Edition(_));
+    if editions && (label == Some(FieldLabel::Required) || label == Some(FieldLabel::Optional)) {
+        return err(
+            "required and optional labels are not allowed in editions, use features.field_presence",
+        );
+    }
+    if next == Token::Group {
+        return parse_group(label, start, comments, scanner, syntax, groups);
+    }
+
+    let (field_type, next) = parse_type(next, scanner)?;
+    if label.is_some() && matches!(field_type, Type::Map { .. }) {
+        return err("map fields cannot have a label");
+    }
+    let name = keyword_ident(next)?;
     expect(scanner, Token::Eq)?;
-    let field_number = expect_decimal_lit(scanner)?;
-    expect(scanner, Token::Semicolon)?;
+    let field_number = expect_field_number(scanner)?;
+
+    let mut options = parse_trailing_options(scanner, Token::Semicolon)?;
+    let span = start.to(scanner.span());
+    comments.trailing = trailing_comment(scanner);
+
+    let default = take_default(&mut options);
+    if let Some(option) = default.as_ref() {
+        if syntax == Syntax::V3 {
+            return err("explicit default values are not allowed in proto3");
+        }
+        //references are allowed, they may point to an enum
+        if label == Some(FieldLabel::Repeated) || matches!(field_type, Type::Map { .. }) {
+            return err(&format!(
+                "field {}: default values are only allowed on singular fields",
+                name
+            ));
+        }
+        check_default(&name, &field_type, option)?;
+    }
+
+    let proto3_optional = syntax == Syntax::V3 && label == Some(FieldLabel::Optional);
     return Ok(Field {
         name,
         field_type,
-        repeated,
+        label,
+        field_number,
+        default: default.map(|o| o.constant),
+        options,
+        proto3_optional,
+        span,
+        comments,
+    });
+}
+
+//group Name = number [options] { body }, after the group keyword.
+//start and comments are those of the label or the group keyword.
+fn parse_group(
+    label: Option<FieldLabel>,
+    start: Span,
+    mut comments: Comments,
+    scanner: &mut Scanner,
+    syntax: Syntax,
+    groups: &mut Vec<Message>,
+) -> Result<Field, ProtoParseError> {
+    if syntax != Syntax::V2 {
+        return err("groups are only allowed in proto2, use features.message_encoding");
+    }
+    let group_name = expect_ident(scanner)?;
+    if !group_name.starts_with(|c: char| c.is_ascii_uppercase()) {
+        return err(&format!(
+            "group name {} must start with a capital letter",
+            group_name
+        ));
+    }
+    expect(scanner, Token::Eq)?;
+    let field_number = expect_field_number(scanner)?;
+
+    let options = parse_trailing_options(scanner, Token::LCurly)?;
+    comments.trailing = trailing_comment(scanner);
+    let group = parse_message_body(group_name.clone(), start, comments.clone(), scanner, syntax)?;
+    let span = group.span;
+    groups.push(group);
+
+    return Ok(Field {
+        name: group_name.to_lowercase(),
+        field_type: Type::Group(group_name),
+        label,
         field_number,
+        default: None,
+        options,
+        proto3_optional: false,
+        span,
+        comments,
     });
 }
 
-fn is_type(token: Token) -> Result<Type, ProtoParseError> {
+//removes the default pseudo-option from the options
+fn take_default(options: &mut Vec<ProtoOption>) -> Option<ProtoOption> {
+    let pos = options.iter().position(|o| o.is_named("default"))?;
+    return Some(options.remove(pos));
+}
+
+//the default value must be a constant of the field type: an integer in the
+//range of integer types, a number for floating point types, a string for
+//string and bytes, and an identifier for enums
+fn check_default(
+    name: &str,
+    field_type: &Type,
+    option: &ProtoOption,
+) -> Result<(), ProtoParseError> {
+    let int_range = match field_type {
+        Type::Int32 | Type::Sint32 | Type::Sfixed32 => Some((i32::MIN as i128, i32::MAX as i128)),
+        Type::Uint32 | Type::Fixed32 => Some((0, u32::MAX as i128)),
+        Type::Int64 | Type::Sint64 | Type::Sfixed64 => Some((i64::MIN as i128, i64::MAX as i128)),
+        Type::Uint64 | Type::Fixed64 => Some((0, u64::MAX as i128)),
+        _ => None,
+    };
+    if let (Some((min, max)), ConstantValue::IntValue(n)) = (int_range, &option.constant) {
+        if *n < min || *n > max {
+            return err_at(
+                option.span,
+                &format!("field {}: default value {} out of range", name, n),
+            );
+        }
+        return Ok(());
+    }
+
+    let (valid, expected) = match field_type {
+        _ if int_range.is_some() => (false, "an integer"),
+        Type::Double | Type::Float => (
+            matches!(
+                option.constant,
+                ConstantValue::IntValue(_) | ConstantValue::FloatValue(_)
+            ),
+            "a number",
+        ),
+        Type::Bool => (
+            matches!(option.constant, ConstantValue::BoolValue(_)),
+            "true or false",
+        ),
+        Type::String => (
+            matches!(option.constant, ConstantValue::StringValue(_)),
+            "a string",
+        ),
+        Type::Bytes => (option.constant.as_bytes().is_some(), "a string"),
+        Type::Reference { .. } => (
+            matches!(&option.constant, ConstantValue::IdentValue(v) if v.idents.len() == 1),
+            "an enum value name",
+        ),
+        _ => (false, "a scalar or enum value"),
+    };
+    if !valid {
+        return err_at(
+            option.span,
+            &format!("field {}: default value must be {}", name, expected),
+        );
+    }
+    return Ok(());
+}
+
+//returns the type and the token that followed it. Keywords other than the
+//scalar types are type names, map is only a map type if followed by <.
+fn parse_type(token: Token, scanner: &mut Scanner) -> Result<(Type, Token), ProtoParseError> {
+    if let Some(scalar) = scalar_type(&token) {
+        return Ok((scalar, scanner.next_token()?));
+    }
+    match token {
+        Token::Map => {
+            let next = scanner.next_token()?;
+            if next == Token::Lt {
+                return parse_map_type(scanner);
+            }
+            let start = scanner.prev_span();
+            let idents = vec!["map".to_string()];
+            return parse_type_reference_rest(idents, start, false, next, scanner);
+        }
+        Token::Dot => parse_type_reference(token, scanner),
+        _ if as_ident(&token).is_some() => parse_type_reference(token, scanner),
+        _ => unexpected(&token, &["type"]),
+    }
+}
+
+//map<keyType, valueType> after the <, returns the type and the token that
+//followed it
+fn parse_map_type(scanner: &mut Scanner) -> Result<(Type, Token), ProtoParseError> {
+    let key_token = scanner.next_token()?;
+    let key = match scalar_type(&key_token) {
+        Some(key) if !matches!(key, Type::Double | Type::Float | Type::Bytes) => key,
+        _ => {
+            return err(&format!(
+                "invalid map key type {}, only integral and string types are allowed",
+                key_token
+            ));
+        }
+    };
+    expect(scanner, Token::Comma)?;
+
+    let value_token = scanner.next_token()?;
+    let (value, next) = parse_type(value_token, scanner)?;
+    if matches!(value, Type::Map { .. }) {
+        return err("map value type cannot be another map");
+    }
+    if next != Token::Gt {
+        return unexpected(&next, &[&Token::Gt.to_string()]);
+    }
+
+    let map = Type::Map {
+        key: Box::new(key),
+        value: Box::new(value),
+    };
+    return Ok((map, scanner.next_token()?));
+}
+
+//[.]{ident.}ident, returns the type and the token that followed it
+fn parse_type_reference(
+    token: Token,
+    scanner: &mut Scanner,
+) -> Result<(Type, Token), ProtoParseError> {
+    let mut next = token;
+    let mut fully_qualified = false;
+    if next == Token::Dot {
+        fully_qualified = true;
+        next = scanner.next_token()?;
+    }
+
+    let start = scanner.span();
+    let idents = vec![keyword_ident(next)?];
+    next = scanner.next_token()?;
+    return parse_type_reference_rest(idents, start, fully_qualified, next, scanner);
+}
+
+//the {.ident} after the first ident of a type reference, start is the span
+//of the first ident
+fn parse_type_reference_rest(
+    mut idents: Vec<String>,
+    start: Span,
+    fully_qualified: bool,
+    mut next: Token,
+    scanner: &mut Scanner,
+) -> Result<(Type, Token), ProtoParseError> {
+    while next == Token::Dot {
+        idents.push(expect_ident(scanner)?);
+        next = scanner.next_token()?;
+    }
+
+    let full_ident = FullIdent {
+        idents,
+        span: start.to(scanner.prev_span()),
+    };
+    let reference = Type::Reference {
+        full_ident,
+        fully_qualified,
+    };
+    return Ok((reference, next));
+}
+
+fn scalar_type(token: &Token) -> Option<Type> {
     match token {
-        Token::TDouble => Ok(Type::Double),
-        Token::TFloat => Ok(Type::Float),
-        Token::TInt32 => Ok(Type::Int32),
-        Token::TInt64 => Ok(Type::Int64),
-        Token::TUint32 => Ok(Type::Uint32),
-        Token::TUint64 => Ok(Type::Uint64),
-        Token::TSint32 => Ok(Type::Sint32),
-        Token::TSint64 => Ok(Type::Sint64),
-        Token::TFixed32 => Ok(Type::Fixed32),
-        Token::TFixed64 => Ok(Type::Fixed64),
-        Token::TSfixed32 => Ok(Type::Sfixed32),
-        Token::TSfixed64 => Ok(Type::Sfixed64),
-        Token::TBool => Ok(Type::Bool),
-        Token::TString => Ok(Type::String),
-        Token::TBytes => Ok(Type::Bytes),
-        _ => err(&format!("unexpected token {:?}, expected type", token)),
-    }
-}
-
-fn expect_decimal_lit(scanner: &mut Scanner) -> Result<u32, ProtoParseError> {
+        Token::TDouble => Some(Type::Double),
+        Token::TFloat => Some(Type::Float),
+        Token::TInt32 => Some(Type::Int32),
+        Token::TInt64 => Some(Type::Int64),
+        Token::TUint32 => Some(Type::Uint32),
+        Token::TUint64 => Some(Type::Uint64),
+        Token::TSint32 => Some(Type::Sint32),
+        Token::TSint64 => Some(Type::Sint64),
+        Token::TFixed32 => Some(Type::Fixed32),
+        Token::TFixed64 => Some(Type::Fixed64),
+        Token::TSfixed32 => Some(Type::Sfixed32),
+        Token::TSfixed64 => Some(Type::Sfixed64),
+        Token::TBool => Some(Type::Bool),
+        Token::TString => Some(Type::String),
+        Token::TBytes => Some(Type::Bytes),
+        _ => None,
+    }
+}
+
+fn expect_field_number(scanner: &mut Scanner) -> Result<u32, ProtoParseError> {
     let next = scanner.next_token()?;
     match next {
-        Token::DecimalLit(u) => Ok(u),
-        _ => err(&format!("DecimalLiteral expected, got {:?}", next)),
+        Token::IntLit(n) if n >= 1 && n <= MAX_FIELD_NUMBER as u64 => Ok(n as u32),
+        Token::IntLit(n) => err(&format!(
+            "field number {} out of range [1, {}]",
+            n, MAX_FIELD_NUMBER
+        )),
+        _ => unexpected(&next, &["integer"]),
     }
 }
 
+//an identifier, keywords are allowed wherever the grammar allows an identifier
 fn expect_ident(scanner: &mut Scanner) -> Result<String, ProtoParseError> {
     let next = scanner.next_token()?;
-    match next {
-        Token::Ident(name) => Ok(name),
-        _ => err(&format!("Ident expected, got {:?}", next)),
+    return keyword_ident(next);
+}
+
+fn keyword_ident(next: Token) -> Result<String, ProtoParseError> {
+    match as_ident(&next) {
+        Some(name) => Ok(name),
+        None => unexpected(&next, &["identifier"]),
     }
 }
 
-//a "real" ident, or a keyword that can be used as an ident
-fn expect_keyword_ident(scanner: &mut Scanner) -> Result<String, ProtoParseError> {
-    let next = scanner.next_token()?;
-    match next {
-        Token::Ident(name) => Ok(name),
-        Token::Message => Ok("message".to_string()),
-        //TODO more keywords are allowed as ident name, list them here
-        _ => err(&format!("Ident expected, got {:?}", next)),
+//the name of an identifier or keyword token
+fn as_ident(token: &Token) -> Option<String> {
+    match token {
+        Token::Ident(name) => Some(name.clone()),
+        _ => token.keyword().map(String::from),
     }
 }
 
@@ -336,10 +1642,7 @@ fn expect(scanner: &mut Scanner, expected: Token) -> Result<Token, ProtoParseErr
     let next = scanner.next_token()?;
 
     if next != expected {
-        return err(&format!(
-            "unexpected token, expected {:?} got {:?}",
-            expected, next
-        ));
+        return unexpected(&next, &[&expected.to_string()]);
     }
     return Ok(next);
 }
@@ -0,0 +1,786 @@
+use super::ast::*;
+
+const MAX_FIELD_NUMBER: i32 = 536_870_911;
+
+//formatting choices of the printer
+#[derive(Debug, Clone)]
+pub struct PrintConfig {
+    //indentation of one nesting level
+    pub indent: String,
+    //pads the names of consecutive fields and enum values so that their =
+    //line up
+    pub align_fields: bool,
+    //prints the imports sorted by path instead of in declaration order
+    pub sort_imports: bool,
+    //declarations with options and option statements with aggregate values
+    //that would be longer are printed with one option or field per line
+    pub max_width: usize,
+}
+
+impl Default for PrintConfig {
+    fn default() -> Self {
+        return PrintConfig {
+            indent: "  ".to_string(),
+            align_fields: true,
+            sort_imports: true,
+            max_width: 100,
+        };
+    }
+}
+
+//prints the definition as .proto source with the default config
+pub fn print(def: &ProtoDef) -> String {
+    return print_with(def, &PrintConfig::default());
+}
+
+//prints the definition in canonical form: syntax, packages, imports and file
+//options first, in blocks the options, reserved and extensions statements come
+//before the other declarations. Declarations are kept in source order (by
+//span), comments are printed as line comments where protoc would attach
+//them again.
+pub fn print_with(def: &ProtoDef, config: &PrintConfig) -> String {
+    let mut printer = Printer {
+        config,
+        syntax: def.syntax,
+        level: 0,
+        out: String::new(),
+        need_blank: false,
+    };
+    printer.print_def(def);
+    return printer.out;
+}
+
+//a declaration in a block, groups are printed with their message
+enum Item<'a> {
+    Field(&'a Field),
+    Group(&'a Field, Option<&'a Message>),
+    OneOf(&'a OneOf),
+    Message(&'a Message),
+    Enum(&'a Enum),
+    Extend(&'a Extend),
+    Service(&'a Service),
+}
+
+impl Item<'_> {
+    fn start(&self) -> usize {
+        match self {
+            Item::Field(f) | Item::Group(f, _) => f.span.start,
+            Item::OneOf(o) => o.span.start,
+            Item::Message(m) => m.span.start,
+            Item::Enum(e) => e.span.start,
+            Item::Extend(e) => e.span.start,
+            Item::Service(s) => s.span.start,
+        }
+    }
+
+    fn comments(&self) -> Option<&Comments> {
+        match self {
+            Item::Field(f) | Item::Group(f, _) => Some(&f.comments),
+            Item::Message(m) => Some(&m.comments),
+            Item::Enum(e) => Some(&e.comments),
+            Item::Service(s) => Some(&s.comments),
+            Item::OneOf(o) => Some(&o.comments),
+            Item::Extend(e) => Some(&e.comments),
+        }
+    }
+}
+
+struct Printer<'a> {
+    config: &'a PrintConfig,
+    syntax: Syntax,
+    level: usize,
+    out: String,
+    //set after a comment that is only attached as trailing comment if a
+    //blank line follows
+    need_blank: bool,
+}
+
+impl Printer<'_> {
+    fn print_def(&mut self, def: &ProtoDef) {
+        self.comments_before(&def.comments);
+        let syntax = match def.syntax {
+            Syntax::V2 => "syntax = \"proto2\";".to_string(),
+            Syntax::V3 => "syntax = \"proto3\";".to_string(),
+            Syntax::Edition(Edition::Edition2023) => "edition = \"2023\";".to_string(),
+            Syntax::Edition(Edition::Edition2024) => "edition = \"2024\";".to_string(),
+        };
+        self.line_with_trailing(&syntax, &def.comments);
+
+        if !def.packages.is_empty() {
+            self.blank();
+            for package in def.packages.iter() {
+                self.comments_before(&package.comments);
+                let text = format!("package {};", package.full_ident.idents.join("."));
+                self.line_with_trailing(&text, &package.comments);
+            }
+        }
+
+        if !def.imports.is_empty() {
+            self.blank();
+            let mut imports: Vec<&Import> = def.imports.iter().collect();
+            if self.config.sort_imports {
+                imports.sort_by(|a, b| a.name.cmp(&b.name));
+            }
+            for import in imports {
+                let kind = match import.import_type {
+                    ImportType::Default => "",
+                    ImportType::Weak => "weak ",
+                    ImportType::Public => "public ",
+                };
+                self.comments_before(&import.comments);
+                let text = format!("import {}{};", kind, quote(&import.name));
+                self.line_with_trailing(&text, &import.comments);
+            }
+        }
+
+        if !def.options.is_empty() {
+            self.blank();
+            self.option_statements(&def.options);
+        }
+
+        let groups = group_names(def.extends.iter().flat_map(|e| e.fields.iter()));
+        let mut items: Vec<Item> = Vec::new();
+        items.extend(def.services.iter().map(Item::Service));
+        items.extend(
+            def.messages
+                .iter()
+                .filter(|m| !groups.contains(&m.name.as_str()))
+                .map(Item::Message),
+        );
+        items.extend(def.enums.iter().map(Item::Enum));
+        items.extend(def.extends.iter().map(Item::Extend));
+        items.sort_by_key(|item| item.start());
+        for item in items.iter() {
+            self.blank();
+            self.item(item, &def.messages, 0);
+        }
+    }
+
+    //prints the items of a block, groups is where the group messages of the
+    //fields are. Fields of a run are aligned to the width of the longest.
+    fn items(&mut self, items: &[Item], groups: &[Message]) {
+        let mut width = 0;
+        for (i, item) in items.iter().enumerate() {
+            let block = !matches!(item, Item::Field(_));
+            let detached = item.comments().is_some_and(|c| !c.detached.is_empty());
+            let prev_block = i > 0 && !matches!(items[i - 1], Item::Field(_));
+            if i > 0 && (block || prev_block || detached) {
+                self.blank();
+            } else if i == 0 && detached {
+                //otherwise the first detached comment would be attached to {
+                self.blank_after_open();
+            }
+
+            if let Item::Field(_) = item
+                && (i == 0 || prev_block)
+            {
+                width = items[i..]
+                    .iter()
+                    .map_while(|item| match item {
+                        Item::Field(field) => Some(field_head(field).len()),
+                        _ => None,
+                    })
+                    .max()
+                    .unwrap_or(0);
+            }
+            self.item(item, groups, width);
+        }
+    }
+
+    fn item(&mut self, item: &Item, groups: &[Message], width: usize) {
+        match item {
+            Item::Field(field) => self.field(field, width),
+            Item::Group(field, message) => self.group(field, *message),
+            Item::OneOf(oneof) => self.oneof(oneof, groups),
+            Item::Message(message) => self.message(message),
+            Item::Enum(enum_def) => self.enum_def(enum_def),
+            Item::Extend(extend) => self.extend(extend, groups),
+            Item::Service(service) => self.service(service),
+        }
+    }
+
+    fn message(&mut self, message: &Message) {
+        self.comments_before(&message.comments);
+        self.open(&format!("message {}", message.name), &message.comments);
+        self.message_body(message);
+        self.close();
+    }
+
+    fn message_body(&mut self, message: &Message) {
+        let mut first = true;
+        if !message.options.is_empty() {
+            self.option_statements(&message.options);
+            first = false;
+        }
+        if !message.reserved.is_empty() {
+            if !first {
+                self.blank();
+            }
+            for reserved in message.reserved.iter() {
+                self.reserved(reserved, MAX_FIELD_NUMBER);
+            }
+            first = false;
+        }
+        if !message.extensions.is_empty() {
+            if !first {
+                self.blank();
+            }
+            for extensions in message.extensions.iter() {
+                self.extensions(extensions);
+            }
+            first = false;
+        }
+
+        let all_fields = message
+            .fields
+            .iter()
+            .chain(message.oneofs.iter().flat_map(|o| o.fields.iter()))
+            .chain(message.extends.iter().flat_map(|e| e.fields.iter()));
+        let groups = group_names(all_fields);
+        let mut items: Vec<Item> = Vec::new();
+        items.extend(
+            message
+                .fields
+                .iter()
+                .map(|f| field_item(f, &message.nested_messages)),
+        );
+        items.extend(message.oneofs.iter().map(Item::OneOf));
+        items.extend(
+            message
+                .nested_messages
+                .iter()
+                .filter(|m| !groups.contains(&m.name.as_str()))
+                .map(Item::Message),
+        );
+        items.extend(message.nested_enums.iter().map(Item::Enum));
+        items.extend(message.extends.iter().map(Item::Extend));
+        items.sort_by_key(|item| item.start());
+        if !first && !items.is_empty() {
+            self.blank();
+        }
+        self.items(&items, &message.nested_messages);
+    }
+
+    fn field(&mut self, field: &Field, width: usize) {
+        self.comments_before(&field.comments);
+        let mut head = field_head(field);
+        if self.config.align_fields {
+            head = format!("{:width$}", head, width = width);
+        }
+        let text = format!("{} = {}", head, field.field_number);
+        let text = self.with_options(&text, &field_options(field), ";");
+        self.line_with_trailing(&text, &field.comments);
+    }
+
+    fn group(&mut self, field: &Field, message: Option<&Message>) {
+        self.comments_before(&field.comments);
+        let name = match &field.field_type {
+            Type::Group(name) => name.as_str(),
+            _ => &field.name,
+        };
+        let mut text = String::new();
+        if let Some(label) = field.label {
+            text.push_str(label_text(label));
+            text.push(' ');
+        }
+        text.push_str(&format!("group {} = {}", name, field.field_number));
+        let text = self.with_options(&text, &option_texts(&field.options), "");
+        self.open(&text, &field.comments);
+        if let Some(message) = message {
+            self.message_body(message);
+        }
+        self.close();
+    }
+
+    fn oneof(&mut self, oneof: &OneOf, groups: &[Message]) {
+        self.comments_before(&oneof.comments);
+        self.open(&format!("oneof {}", oneof.name), &oneof.comments);
+        self.option_statements(&oneof.options);
+        if !oneof.options.is_empty() && !oneof.fields.is_empty() {
+            self.blank();
+        }
+        let items: Vec<Item> = oneof.fields.iter().map(|f| field_item(f, groups)).collect();
+        self.items(&items, groups);
+        self.close();
+    }
+
+    fn extend(&mut self, extend: &Extend, groups: &[Message]) {
+        let name = extend.extendee.idents.join(".");
+        let dot = if extend.fully_qualified { "." } else { "" };
+        self.comments_before(&extend.comments);
+        self.open(&format!("extend {}{}", dot, name), &extend.comments);
+        let items: Vec<Item> = extend
+            .fields
+            .iter()
+            .map(|f| field_item(f, groups))
+            .collect();
+        self.items(&items, groups);
+        self.close();
+    }
+
+    fn enum_def(&mut self, enum_def: &Enum) {
+        self.comments_before(&enum_def.comments);
+        self.open(&format!("enum {}", enum_def.name), &enum_def.comments);
+        let mut first = true;
+        if !enum_def.options.is_empty() {
+            self.option_statements(&enum_def.options);
+            first = false;
+        }
+        if !enum_def.reserved.is_empty() {
+            if !first {
+                self.blank();
+            }
+            for reserved in enum_def.reserved.iter() {
+                self.reserved(reserved, i32::MAX);
+            }
+            first = false;
+        }
+        if !first && !enum_def.values.is_empty() {
+            self.blank();
+        }
+
+        let width = enum_def
+            .values
+            .iter()
+            .map(|v| v.name.len())
+            .max()
+            .unwrap_or(0);
+        for (i, value) in enum_def.values.iter().enumerate() {
+            if !value.comments.detached.is_empty() {
+                if i == 0 {
+                    self.blank_after_open();
+                } else {
+                    self.blank();
+                }
+            }
+            self.comments_before(&value.comments);
+            let name = if self.config.align_fields {
+                format!("{:width$}", value.name, width = width)
+            } else {
+                value.name.clone()
+            };
+            let text = format!("{} = {}", name, value.number);
+            let text = self.with_options(&text, &option_texts(&value.options), ";");
+            self.line_with_trailing(&text, &value.comments);
+        }
+        self.close();
+    }
+
+    fn service(&mut self, service: &Service) {
+        self.comments_before(&service.comments);
+        self.open(&format!("service {}", service.name), &service.comments);
+        self.option_statements(&service.options);
+        for (i, rpc) in service.rpcs.iter().enumerate() {
+            let detached = !rpc.comments.detached.is_empty();
+            //rpcs with options are blocks
+            let block = !rpc.options.is_empty();
+            let prev_block = i > 0 && !service.rpcs[i - 1].options.is_empty();
+            if (i == 0 && !service.options.is_empty())
+                || (i > 0 && (detached || block || prev_block))
+            {
+                self.blank();
+            } else if i == 0 && detached {
+                self.blank_after_open();
+            }
+            self.rpc(rpc);
+        }
+        self.close();
+    }
+
+    fn rpc(&mut self, rpc: &Rpc) {
+        self.comments_before(&rpc.comments);
+        let stream = |streaming| if streaming { "stream " } else { "" };
+        let dot = |fully_qualified| if fully_qualified { "." } else { "" };
+        let text = format!(
+            "rpc {}({}{}{}) returns ({}{}{})",
+            rpc.name,
+            stream(rpc.client_streaming),
+            dot(rpc.request_fully_qualified),
+            rpc.request_type.idents.join("."),
+            stream(rpc.server_streaming),
+            dot(rpc.response_fully_qualified),
+            rpc.response_type.idents.join(".")
+        );
+        if rpc.options.is_empty() {
+            self.line_with_trailing(&format!("{};", text), &rpc.comments);
+            return;
+        }
+        self.open(&text, &rpc.comments);
+        self.option_statements(&rpc.options);
+        self.close();
+    }
+
+    fn reserved(&mut self, reserved: &Reserved, max: i32) {
+        let items: Vec<String> = if !reserved.ranges.is_empty() {
+            reserved
+                .ranges
+                .iter()
+                .map(|r| range_text(r.start, r.end, max))
+                .collect()
+        } else if matches!(self.syntax, Syntax::Edition(_)) {
+            //editions use identifiers instead of strings
+            reserved.names.clone()
+        } else {
+            reserved.names.iter().map(|n| quote(n)).collect()
+        };
+        self.comments_before(&reserved.comments);
+        let text = format!("reserved {};", items.join(", "));
+        self.line_with_trailing(&text, &reserved.comments);
+    }
+
+    fn extensions(&mut self, extensions: &Extensions) {
+        self.comments_before(&extensions.comments);
+        let ranges: Vec<String> = extensions
+            .ranges
+            .iter()
+            .map(|r| range_text(r.start, r.end, MAX_FIELD_NUMBER))
+            .collect();
+        let text = format!("extensions {}", ranges.join(", "));
+        let text = self.with_options(&text, &option_texts(&extensions.options), ";");
+        self.line_with_trailing(&text, &extensions.comments);
+    }
+
+    fn option_statements(&mut self, options: &[ProtoOption]) {
+        for option in options.iter() {
+            self.comments_before(&option.comments);
+            let head = format!("option {} = ", option.name_text());
+            let value = constant_text(&option.constant);
+            let indent = self.config.indent.len() * self.level;
+            match &option.constant {
+                ConstantValue::AggregateValue(fields)
+                    if indent + head.len() + value.len() + 1 > self.config.max_width =>
+                {
+                    self.line(&format!("{}{{", head));
+                    self.level += 1;
+                    self.aggregate_fields(fields);
+                    self.level -= 1;
+                    self.line_with_trailing("};", &option.comments);
+                }
+                _ => self.line_with_trailing(&format!("{}{};", head, value), &option.comments),
+            }
+        }
+    }
+
+    //one field per line, aggregate values that don't fit are expanded too
+    fn aggregate_fields(&mut self, fields: &[AggregateField]) {
+        for field in fields.iter() {
+            let name = aggregate_name(field);
+            let indent = self.config.indent.len() * self.level;
+            match &field.value {
+                ConstantValue::AggregateValue(nested)
+                    if indent + name.len() + constant_text(&field.value).len() + 1
+                        > self.config.max_width =>
+                {
+                    self.line(&format!("{} {{", name));
+                    self.level += 1;
+                    self.aggregate_fields(nested);
+                    self.level -= 1;
+                    self.line("}");
+                }
+                ConstantValue::AggregateValue(_) => {
+                    self.line(&format!("{} {}", name, constant_text(&field.value)));
+                }
+                value => self.line(&format!("{}: {}", name, constant_text(value))),
+            }
+        }
+    }
+
+    //text followed by the [options] and term, or the options one per line if
+    //that is too long. The wrapped options are added to the output, only
+    //the last line is returned.
+    fn with_options(&mut self, text: &str, options: &[String], term: &str) -> String {
+        if options.is_empty() {
+            return format!("{}{}", text, term);
+        }
+        let inline = format!("{} [{}]{}", text, options.join(", "), term);
+        let indent = self.config.indent.len() * self.level;
+        if indent + inline.len() <= self.config.max_width {
+            return inline;
+        }
+        self.line(&format!("{} [", text));
+        self.level += 1;
+        for (i, option) in options.iter().enumerate() {
+            let comma = if i + 1 < options.len() { "," } else { "" };
+            self.line(&format!("{}{}", option, comma));
+        }
+        self.level -= 1;
+        return format!("]{}", term);
+    }
+
+    //prints text followed by { and starts the block
+    fn open(&mut self, text: &str, comments: &Comments) {
+        //a trailing comment of { spanning lines goes into the block
+        self.line_with_trailing_in(&format!("{} {{", text), comments, 1);
+    }
+
+    fn close(&mut self) {
+        self.level -= 1;
+        self.need_blank = false;
+        if self.out.ends_with("{\n") {
+            //empty block
+            self.out.truncate(self.out.len() - 1);
+            self.out.push_str("}\n");
+            return;
+        }
+        self.line("}");
+    }
+
+    fn comments_before(&mut self, comments: &Comments) {
+        //otherwise the first detached comment would be the trailing comment
+        //of the line before
+        if !comments.detached.is_empty() {
+            if self.out.ends_with("{\n") {
+                self.blank_after_open();
+            } else {
+                self.blank();
+            }
+        }
+        for comment in comments.detached.iter() {
+            self.comment_lines(comment);
+            self.blank();
+        }
+        if let Some(comment) = &comments.leading {
+            self.comment_lines(comment);
+        }
+    }
+
+    fn line_with_trailing(&mut self, text: &str, comments: &Comments) {
+        self.line_with_trailing_in(text, comments, 0);
+    }
+
+    //prints the line with the trailing comment. A comment of several lines
+    //is put below at level + extra_level and must be followed by a blank line
+    //to be attached to the line again.
+    fn line_with_trailing_in(&mut self, text: &str, comments: &Comments, extra_level: usize) {
+        let trailing = match &comments.trailing {
+            Some(trailing) => trailing,
+            None => {
+                self.line(text);
+                self.level += extra_level;
+                return;
+            }
+        };
+        let body = trailing.strip_suffix('\n').unwrap_or(trailing);
+        if !body.contains('\n') {
+            self.line(&format!("{} //{}", text, body.trim_end()));
+            self.level += extra_level;
+            return;
+        }
+        self.line(text);
+        self.level += extra_level;
+        self.comment_lines(trailing);
+        self.need_blank = true;
+    }
+
+    //one line comment per line, only the newline ending the last line is
+    //dropped, so empty // lines at the end are kept
+    fn comment_lines(&mut self, comment: &str) {
+        for line in comment.strip_suffix('\n').unwrap_or(comment).split('\n') {
+            self.line(&format!("//{}", line.trim_end()));
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        if self.need_blank {
+            self.need_blank = false;
+            self.out.push('\n');
+        }
+        for _ in 0..self.level {
+            self.out.push_str(&self.config.indent);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    //an empty line, unless at the start of the output or after one
+    fn blank(&mut self) {
+        self.need_blank = false;
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") && !self.out.ends_with("{\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn blank_after_open(&mut self) {
+        if !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+        self.need_blank = false;
+    }
+}
+
+fn field_item<'a>(field: &'a Field, groups: &'a [Message]) -> Item<'a> {
+    match &field.field_type {
+        Type::Group(name) => Item::Group(field, groups.iter().find(|m| m.name == *name)),
+        _ => Item::Field(field),
+    }
+}
+
+//names of the group messages of the fields
+fn group_names<'a>(fields: impl Iterator<Item = &'a Field>) -> Vec<&'a str> {
+    return fields
+        .filter_map(|f| match &f.field_type {
+            Type::Group(name) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+}
+
+//label, type and name of a field
+fn field_head(field: &Field) -> String {
+    let mut head = String::new();
+    if let Some(label) = field.label {
+        head.push_str(label_text(label));
+        head.push(' ');
+    }
+    head.push_str(&type_text(&field.field_type));
+    head.push(' ');
+    head.push_str(&field.name);
+    return head;
+}
+
+fn label_text(label: FieldLabel) -> &'static str {
+    match label {
+        FieldLabel::Required => "required",
+        FieldLabel::Optional => "optional",
+        FieldLabel::Repeated => "repeated",
+    }
+}
+
+fn type_text(field_type: &Type) -> String {
+    let name = match field_type {
+        Type::Double => "double",
+        Type::Float => "float",
+        Type::Int32 => "int32",
+        Type::Int64 => "int64",
+        Type::Uint32 => "uint32",
+        Type::Uint64 => "uint64",
+        Type::Sint32 => "sint32",
+        Type::Sint64 => "sint64",
+        Type::Fixed32 => "fixed32",
+        Type::Fixed64 => "fixed64",
+        Type::Sfixed32 => "sfixed32",
+        Type::Sfixed64 => "sfixed64",
+        Type::Bool => "bool",
+        Type::String => "string",
+        Type::Bytes => "bytes",
+        Type::Reference {
+            full_ident,
+            fully_qualified,
+        } => {
+            let dot = if *fully_qualified { "." } else { "" };
+            return format!("{}{}", dot, full_ident.idents.join("."));
+        }
+        Type::Map { key, value } => {
+            return format!("map<{}, {}>", type_text(key), type_text(value));
+        }
+        Type::Group(name) => return name.clone(),
+    };
+    return name.to_string();
+}
+
+//the options of a field, starting with the default value
+fn field_options(field: &Field) -> Vec<String> {
+    let mut options = Vec::new();
+    if let Some(default) = &field.default {
+        options.push(format!("default = {}", constant_text(default)));
+    }
+    options.extend(option_texts(&field.options));
+    return options;
+}
+
+fn option_texts(options: &[ProtoOption]) -> Vec<String> {
+    return options
+        .iter()
+        .map(|o| format!("{} = {}", o.name_text(), constant_text(&o.constant)))
+        .collect();
+}
+
+fn range_text(start: i32, end: i32, max: i32) -> String {
+    if start == end {
+        return start.to_string();
+    }
+    if end == max {
+        return format!("{} to max", start);
+    }
+    return format!("{} to {}", start, end);
+}
+
+//the constant on a single line
+fn constant_text(value: &ConstantValue) -> String {
+    match value {
+        ConstantValue::IdentValue(ident) => ident.idents.join("."),
+        ConstantValue::IntValue(n) => n.to_string(),
+        ConstantValue::FloatValue(f) if f.is_nan() => "nan".to_string(),
+        ConstantValue::FloatValue(f) if f.is_infinite() && *f > 0.0 => "inf".to_string(),
+        ConstantValue::FloatValue(f) if f.is_infinite() => "-inf".to_string(),
+        //debug formatting keeps the fraction of integral values, e.g. 1.0
+        ConstantValue::FloatValue(f) => format!("{:?}", f),
+        ConstantValue::StringValue(s) => quote(s),
+        ConstantValue::BytesValue(b) => quote_bytes(b),
+        ConstantValue::BoolValue(b) => b.to_string(),
+        ConstantValue::AggregateValue(fields) if fields.is_empty() => "{}".to_string(),
+        ConstantValue::AggregateValue(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|f| match &f.value {
+                    ConstantValue::AggregateValue(_) => {
+                        format!("{} {}", aggregate_name(f), constant_text(&f.value))
+                    }
+                    value => format!("{}: {}", aggregate_name(f), constant_text(value)),
+                })
+                .collect();
+            format!("{{ {} }}", fields.join(" "))
+        }
+        ConstantValue::ListValue(values) => {
+            let values: Vec<String> = values.iter().map(constant_text).collect();
+            format!("[{}]", values.join(", "))
+        }
+    }
+}
+
+fn aggregate_name(field: &AggregateField) -> String {
+    let name = field.name.idents.join(".");
+    if field.extension {
+        return format!("[{}]", name);
+    }
+    return name;
+}
+
+fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let mut buf = [0; 4];
+                for b in c.encode_utf8(&mut buf).bytes() {
+                    quoted.push_str(&format!("\\{:03o}", b));
+                }
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    return quoted;
+}
+
+fn quote_bytes(bytes: &[u8]) -> String {
+    let mut quoted = String::from("\"");
+    for b in bytes.iter() {
+        match b {
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            b'\n' => quoted.push_str("\\n"),
+            b'\r' => quoted.push_str("\\r"),
+            b'\t' => quoted.push_str("\\t"),
+            0x20..=0x7e => quoted.push(*b as char),
+            _ => quoted.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    quoted.push('"');
+    return quoted;
+}
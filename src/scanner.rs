@@ -1,16 +1,21 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::iter::Peekable;
 use std::result::Result;
 use std::str;
 use std::str::Chars;
 
-use super::error::{ProtoParseError, err};
+use super::cst::{Builder, Node, NodeKind, TokenKind};
+use super::error::{ProtoParseError, lexical_err};
+use super::span::Span;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     Syntax,
     Eq,
     StrLit(String),
+    //string literal whose escapes don't decode to valid UTF-8
+    BytesLit(Vec<u8>),
     Import,
     Package,
     Ident(String),
@@ -30,13 +35,25 @@ pub enum Token {
     Option,
     Enum,
     BoolLit(bool),
-    DecimalLit(u32),
+    IntLit(u64),
+    FloatLit(f64),
     LBracket,
     RBracket,
     Lt,
     Gt,
     Map,
     Repeated,
+    OneOf,
+    Reserved,
+    To,
+    Max,
+    Stream,
+    Required,
+    Optional,
+    Group,
+    Edition,
+    Extend,
+    Extensions,
     //types
     TDouble,
     TFloat,
@@ -56,49 +73,264 @@ pub enum Token {
     EOF,
     Plus,
     Minus,
+    Colon,
 }
 
 //use rust_phf once feature(plugin) can be used in the non-nightly
 lazy_static! {
     static ref IDENT_MAP: HashMap<&'static str, Token> = {
         let mut map = HashMap::new();
-        map.insert("syntax".into(), Token::Syntax);
-        map.insert("import".into(), Token::Import);
-        map.insert("package".into(), Token::Package);
-        map.insert("service".into(), Token::Service);
-        map.insert("rpc".into(), Token::Rpc);
-        map.insert("returns".into(), Token::Returns);
-        map.insert("message".into(), Token::Message);
-        map.insert("public".into(), Token::Public);
-        map.insert("weak".into(), Token::Weak);
-        map.insert("option".into(), Token::Option);
-        map.insert("enum".into(), Token::Enum);
-        map.insert("true".into(), Token::BoolLit(true));
-        map.insert("false".into(), Token::BoolLit(false));
-        map.insert("map".into(), Token::Map);
-        map.insert("repeated".into(), Token::Repeated);
-
-        map.insert("double".into(), Token::TDouble);
-        map.insert("float".into(), Token::TFloat);
-        map.insert("int32".into(), Token::TInt32);
-        map.insert("int64".into(), Token::TInt64);
-        map.insert("uint32".into(), Token::TUint32);
-        map.insert("uint64".into(), Token::TUint64);
-        map.insert("sint32".into(), Token::TSint32);
-        map.insert("sint64".into(), Token::TSint64);
-        map.insert("fixed32".into(), Token::TFixed32);
-        map.insert("fixed64".into(), Token::TFixed64);
-        map.insert("sfixed32".into(), Token::TSfixed32);
-        map.insert("sfixed64".into(), Token::TSfixed64);
-        map.insert("bool".into(), Token::TBool);
-        map.insert("string".into(), Token::TString);
-        map.insert("bytes".into(), Token::TBytes);
+        map.insert("syntax", Token::Syntax);
+        map.insert("import", Token::Import);
+        map.insert("package", Token::Package);
+        map.insert("service", Token::Service);
+        map.insert("rpc", Token::Rpc);
+        map.insert("returns", Token::Returns);
+        map.insert("message", Token::Message);
+        map.insert("public", Token::Public);
+        map.insert("weak", Token::Weak);
+        map.insert("option", Token::Option);
+        map.insert("enum", Token::Enum);
+        map.insert("true", Token::BoolLit(true));
+        map.insert("false", Token::BoolLit(false));
+        map.insert("map", Token::Map);
+        map.insert("repeated", Token::Repeated);
+        map.insert("oneof", Token::OneOf);
+        map.insert("reserved", Token::Reserved);
+        map.insert("to", Token::To);
+        map.insert("max", Token::Max);
+        map.insert("stream", Token::Stream);
+        map.insert("required", Token::Required);
+        map.insert("optional", Token::Optional);
+        map.insert("group", Token::Group);
+        map.insert("edition", Token::Edition);
+        map.insert("extend", Token::Extend);
+        map.insert("extensions", Token::Extensions);
+
+        map.insert("double", Token::TDouble);
+        map.insert("float", Token::TFloat);
+        map.insert("int32", Token::TInt32);
+        map.insert("int64", Token::TInt64);
+        map.insert("uint32", Token::TUint32);
+        map.insert("uint64", Token::TUint64);
+        map.insert("sint32", Token::TSint32);
+        map.insert("sint64", Token::TSint64);
+        map.insert("fixed32", Token::TFixed32);
+        map.insert("fixed64", Token::TFixed64);
+        map.insert("sfixed32", Token::TSfixed32);
+        map.insert("sfixed64", Token::TSfixed64);
+        map.insert("bool", Token::TBool);
+        map.insert("string", Token::TString);
+        map.insert("bytes", Token::TBytes);
         map
     };
 }
 
+impl Token {
+    //the text of a keyword token, e.g. "message" for Token::Message
+    pub fn keyword(&self) -> Option<&'static str> {
+        return IDENT_MAP
+            .iter()
+            .find(|(_, t)| *t == self)
+            .map(|(name, _)| *name);
+    }
+}
+
+//the description of a token in error messages, e.g. `;` or identifier `foo`
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Token::StrLit(s) => return write!(f, "string literal {:?}", s),
+            Token::BytesLit(_) => return write!(f, "string literal"),
+            Token::Ident(s) => return write!(f, "identifier `{}`", s),
+            Token::IntLit(n) => return write!(f, "integer `{}`", n),
+            Token::FloatLit(n) => return write!(f, "float `{}`", n),
+            Token::EOF => return write!(f, "end of input"),
+            Token::Eq => "=",
+            Token::LCurly => "{",
+            Token::RCurly => "}",
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
+            Token::Lt => "<",
+            Token::Gt => ">",
+            Token::Semicolon => ";",
+            Token::Dot => ".",
+            Token::Comma => ",",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Colon => ":",
+            keyword => keyword.keyword().unwrap_or("token"),
+        };
+        return write!(f, "`{}`", symbol);
+    }
+}
+
+//a token and its location in the source
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
 pub struct Scanner<'a> {
-    buf: Peekable<Chars<'a>>,
+    source: &'a str,
+    buf: Cursor<'a>,
+    //span of the last token returned and the one before it
+    span: Span,
+    prev_span: Span,
+    //the last token, None if scanning it failed
+    token: Option<Token>,
+    //number of { not closed yet
+    depth: usize,
+    //errors reported by the parser while recovering
+    diagnostics: Vec<ProtoParseError>,
+    //comments of the last token
+    leading: Option<String>,
+    detached: Vec<String>,
+    trailing: Option<String>,
+    //comments already scanned for the next token
+    next_leading: Option<String>,
+    next_detached: Vec<String>,
+    //false if the trivia before the next token still has to be scanned
+    trivia_scanned: bool,
+    //error in the comments after the last token, returned by the next call
+    trivia_error: Option<ProtoParseError>,
+    //None unless created with with_cst
+    cst: Option<Builder>,
+    //byte offset up to which the source was added to the cst
+    recorded: usize,
+}
+
+enum Comment {
+    Line(String),
+    Block(String),
+}
+
+//groups the comments between two tokens, port of protoc's CommentCollector
+struct CommentCollector {
+    buffer: String,
+    has_comment: bool,
+    is_line_comment: bool,
+    //false once a comment can no longer be the trailing comment of the
+    //previous token
+    can_attach_to_prev: bool,
+    trailing: Option<String>,
+    detached: Vec<String>,
+    num_comments: usize,
+}
+
+impl CommentCollector {
+    fn new() -> CommentCollector {
+        return CommentCollector {
+            buffer: String::new(),
+            has_comment: false,
+            is_line_comment: false,
+            can_attach_to_prev: true,
+            trailing: None,
+            detached: Vec::new(),
+            num_comments: 0,
+        };
+    }
+
+    //consecutive line comments form a single comment
+    fn line_comment(&mut self, text: &str) {
+        if self.has_comment && !self.is_line_comment {
+            self.flush();
+        }
+        self.has_comment = true;
+        self.is_line_comment = true;
+        self.buffer.push_str(text);
+    }
+
+    fn block_comment(&mut self, text: &str) {
+        self.flush();
+        self.has_comment = true;
+        self.is_line_comment = false;
+        self.buffer.push_str(text);
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+        self.has_comment = false;
+    }
+
+    //ends the current comment, it becomes the trailing comment of the
+    //previous token if possible and is detached otherwise
+    fn flush(&mut self) {
+        if !self.has_comment {
+            return;
+        }
+        let comment = std::mem::take(&mut self.buffer);
+        if self.can_attach_to_prev {
+            self.trailing = Some(comment);
+            self.can_attach_to_prev = false;
+        } else {
+            self.detached.push(comment);
+        }
+        self.clear();
+        self.num_comments += 1;
+    }
+
+    fn detach_from_prev(&mut self) {
+        self.can_attach_to_prev = false;
+    }
+
+    //the next token is on the line the previous token or its trailing
+    //comment ended, a single comment is then detached from both
+    fn maybe_detach_comment(&mut self) {
+        let count = self.num_comments + self.has_comment as usize;
+        if count == 1 {
+            if let Some(comment) = self.trailing.take() {
+                self.detached.insert(0, comment);
+            }
+            self.detach_from_prev();
+            self.flush();
+        }
+    }
+}
+
+//char iterator that keeps track of the byte offset, line and column
+#[derive(Clone)]
+struct Cursor<'a> {
+    chars: Peekable<Chars<'a>>,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Cursor<'_> {
+    fn peek(&mut self) -> Option<&char> {
+        return self.chars.peek();
+    }
+
+    fn next_if(&mut self, pred: impl FnOnce(&char) -> bool) -> Option<char> {
+        match self.chars.peek() {
+            Some(c) if pred(c) => return self.next(),
+            _ => return None,
+        }
+    }
+
+    fn next_if_eq(&mut self, expected: &char) -> Option<char> {
+        return self.next_if(|c| c == expected);
+    }
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        return Some(c);
+    }
 }
 
 fn non_ident_char(c: char) -> bool {
@@ -115,193 +347,701 @@ fn non_ident_char(c: char) -> bool {
         || c == '>'
         || c == '.'
         || c == '+'
-        || c == '-';
+        || c == '-'
+        || c == ':'
+        || c == '/';
 }
 
 impl<'a> Scanner<'a> {
-    pub fn new(buffer: &'a String) -> Scanner<'a> {
+    pub fn new(buffer: &'a str) -> Scanner<'a> {
         return Scanner {
-            buf: buffer.chars().peekable(),
+            source: buffer,
+            buf: Cursor {
+                chars: buffer.chars().peekable(),
+                offset: 0,
+                line: 1,
+                column: 1,
+            },
+            span: Span::new(0, 0, 1, 1),
+            prev_span: Span::new(0, 0, 1, 1),
+            token: None,
+            depth: 0,
+            diagnostics: Vec::new(),
+            leading: None,
+            detached: Vec::new(),
+            trailing: None,
+            next_leading: None,
+            next_detached: Vec::new(),
+            trivia_scanned: false,
+            trivia_error: None,
+            cst: None,
+            recorded: 0,
         };
     }
 
+    //like new, also builds the lossless syntax tree of the source, see
+    //finish_cst
+    pub fn with_cst(buffer: &'a str) -> Scanner<'a> {
+        let mut scanner = Scanner::new(buffer);
+        scanner.cst = Some(Builder::new());
+        return scanner;
+    }
+
+    //span of the token last returned by next_token
+    pub fn span(&self) -> Span {
+        return self.span;
+    }
+
+    //span of the token before the last one
+    pub fn prev_span(&self) -> Span {
+        return self.prev_span;
+    }
+
+    //the token last returned by next_token, None if it was a lexical error
+    pub fn token(&self) -> Option<&Token> {
+        return self.token.as_ref();
+    }
+
+    //number of { tokens returned that were not closed by a } yet
+    pub fn depth(&self) -> usize {
+        return self.depth;
+    }
+
+    //records an error and continues, errors without a location point to the
+    //last token. Only the first error at a location is kept.
+    pub fn report(&mut self, error: ProtoParseError) {
+        let error = error.or_span(self.span);
+        if self.diagnostics.last().and_then(|e| e.span()) != error.span() {
+            self.diagnostics.push(error);
+        }
+    }
+
+    pub fn take_diagnostics(&mut self) -> Vec<ProtoParseError> {
+        return std::mem::take(&mut self.diagnostics);
+    }
+
+    //opens a syntax tree node that starts with the last token
+    pub fn start_node(&mut self, kind: NodeKind) {
+        if let Some(cst) = &mut self.cst {
+            cst.start_node(kind);
+        }
+    }
+
+    //number of open syntax tree nodes
+    pub fn node_depth(&self) -> usize {
+        return self.cst.as_ref().map_or(0, Builder::depth);
+    }
+
+    //closes the syntax tree nodes until depth nodes are left
+    pub fn finish_nodes(&mut self, depth: usize) {
+        if let Some(cst) = &mut self.cst {
+            cst.finish_nodes(depth);
+        }
+    }
+
+    //the syntax tree of the source read so far, the rest of the source is
+    //added as long as the scanner has not reached the end. None if the
+    //scanner was not created with with_cst.
+    pub fn finish_cst(self) -> Option<Node> {
+        let mut cst = self.cst?;
+        cst.finish_nodes(1);
+        if self.recorded < self.source.len() {
+            cst.token(TokenKind::Error, &self.source[self.recorded..]);
+        }
+        return Some(cst.finish());
+    }
+
+    //comment directly before the last token
+    pub fn leading_comment(&self) -> Option<&str> {
+        return self.leading.as_deref();
+    }
+
+    //comments before the last token that are separated from it and from
+    //the token before by blank lines
+    pub fn detached_comments(&self) -> &[String] {
+        return &self.detached;
+    }
+
+    //comment after the last token, on the same line or on the lines below
+    //up to a blank line
+    pub fn trailing_comment(&self) -> Option<&str> {
+        return self.trailing.as_deref();
+    }
+
+    //errors point to the text scanned for the failed token
     pub fn next_token(&mut self) -> Result<Token, ProtoParseError> {
-        self.unread_whitespace();
+        return self.next_spanned_token().map(|t| t.token);
+    }
+
+    //like next_token, also returns where the token is in the source
+    pub fn next_spanned_token(&mut self) -> Result<SpannedToken, ProtoParseError> {
+        self.trailing = None;
+        if let Some(e) = self.trivia_error.take() {
+            self.token = None;
+            return Err(e);
+        }
+        //at the start of the input or after a lexical error there is no
+        //previous token the comments could belong to
+        if !self.trivia_scanned
+            && let Err(e) = self.scan_trivia(false)
+        {
+            self.token = None;
+            return Err(e);
+        }
+        self.trivia_scanned = false;
+        self.leading = self.next_leading.take();
+        self.detached = std::mem::take(&mut self.next_detached);
 
+        let (start, line, column) = (self.buf.offset, self.buf.line, self.buf.column);
+        let token = self.scan_token();
+
+        //a string literal that failed at a line break has consumed it
+        let end = start + self.source[start..self.buf.offset].trim_end().len();
+        let span = Span::new(start, end, line, column);
+        self.prev_span = self.span;
+        self.span = span;
+        match token {
+            Ok(Token::LCurly) => self.depth += 1,
+            Ok(Token::RCurly) => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+        self.token = token.as_ref().ok().cloned();
+        self.record_token(end);
+        let token = token.map_err(|e| e.or_span(span))?;
+
+        match self.scan_trivia(true) {
+            Ok(()) => self.trivia_scanned = true,
+            Err(e) => self.trivia_error = Some(e),
+        }
+        return Ok(SpannedToken { token, span });
+    }
+
+    //scans the whitespace and comments up to the next token and attributes
+    //the comments like protoc does: a comment on the line of the previous
+    //token, or one below it that is followed by a blank line, is the trailing
+    //comment of that token. The comment directly before the next token is its
+    //leading comment, all others are detached.
+    fn scan_trivia(&mut self, after_token: bool) -> Result<(), ProtoParseError> {
+        let mut collector = CommentCollector::new();
+        let prev_line = self.buf.line;
+        let mut trailing_end_line = None;
+
+        if !after_token {
+            collector.detach_from_prev();
+        } else {
+            self.unread_blank();
+            match self.read_comment()? {
+                Some(Comment::Line(text)) => {
+                    trailing_end_line = Some(prev_line);
+                    collector.line_comment(&text);
+                    collector.flush();
+                }
+                Some(Comment::Block(text)) => {
+                    collector.block_comment(&text);
+                    trailing_end_line = Some(self.buf.line);
+                    self.unread_blank();
+                    if self.buf.next_if_eq(&'\n').is_none() {
+                        //the comment is followed by a token on the same line
+                        collector.clear();
+                        self.finish_trivia(collector);
+                        return Ok(());
+                    }
+                    collector.flush();
+                }
+                None => {
+                    if self.buf.next_if_eq(&'\n').is_none() {
+                        self.finish_trivia(collector);
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        loop {
+            self.unread_blank();
+            match self.read_comment()? {
+                Some(Comment::Line(text)) => collector.line_comment(&text),
+                Some(Comment::Block(text)) => {
+                    collector.block_comment(&text);
+                    self.unread_blank();
+                    self.buf.next_if_eq(&'\n');
+                }
+                None => {
+                    if self.buf.next_if_eq(&'\n').is_some() {
+                        //a blank line ends a comment block
+                        collector.flush();
+                        collector.detach_from_prev();
+                        continue;
+                    }
+                    let next = self.buf.peek().copied();
+                    if matches!(next, None | Some('}') | Some(']') | Some(')')) {
+                        //no leading comment for the end of a block
+                        collector.flush();
+                    }
+                    if next.is_some()
+                        && (self.buf.line == prev_line || Some(self.buf.line) == trailing_end_line)
+                    {
+                        collector.maybe_detach_comment();
+                    }
+                    self.finish_trivia(collector);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn finish_trivia(&mut self, collector: CommentCollector) {
+        self.record_trivia(TokenKind::Whitespace);
+        self.trailing = collector.trailing;
+        self.next_detached = collector.detached;
+        self.next_leading = if collector.has_comment {
+            Some(collector.buffer)
+        } else {
+            None
+        };
+    }
+
+    fn scan_token(&mut self) -> Result<Token, ProtoParseError> {
         let mut token = String::new();
-        let mut str_lit = false;
-        let mut dec_lit = false;
-        let mut escaped = false;
         loop {
-            let peek = self.buf.peek().map(|c| *c);
+            let peek = self.buf.peek().copied();
             match peek {
                 None => {
-                    if str_lit {
-                        return err("Lexical error: unclosed string literal");
+                    if !token.is_empty() {
+                        break;
                     }
                     return Ok(Token::EOF);
                 }
                 Some(c) => {
-                    if str_lit {
+                    if token.is_empty() {
                         match c {
-                            // TODO there are more escaping chars in proto
-                            '"' => {
-                                if escaped {
-                                    self.buf.next();
-                                    token.push(c);
-                                    escaped = false;
-                                } else {
-                                    self.buf.next();
-                                    break;
-                                }
+                            '{' => {
+                                self.buf.next();
+                                return Ok(Token::LCurly);
+                            }
+                            '}' => {
+                                self.buf.next();
+                                return Ok(Token::RCurly);
+                            }
+                            '=' => {
+                                self.buf.next();
+                                return Ok(Token::Eq);
+                            }
+                            '(' => {
+                                self.buf.next();
+                                return Ok(Token::LParen);
+                            }
+                            ')' => {
+                                self.buf.next();
+                                return Ok(Token::RParen);
+                            }
+                            ',' => {
+                                self.buf.next();
+                                return Ok(Token::Comma);
+                            }
+                            ';' => {
+                                self.buf.next();
+                                return Ok(Token::Semicolon);
                             }
-                            '\\' => {
-                                if escaped {
-                                    self.buf.next();
-                                    token.push(c);
-                                    escaped = false;
-                                } else {
-                                    escaped = true;
-                                    self.buf.next();
+                            '.' => {
+                                self.buf.next();
+                                if self.buf.peek().is_some_and(|c| c.is_ascii_digit()) {
+                                    return self.scan_number(".".to_string());
                                 }
+                                return Ok(Token::Dot);
+                            }
+                            '[' => {
+                                self.buf.next();
+                                return Ok(Token::LBracket);
+                            }
+                            ']' => {
+                                self.buf.next();
+                                return Ok(Token::RBracket);
+                            }
+                            '<' => {
+                                self.buf.next();
+                                return Ok(Token::Lt);
+                            }
+                            '>' => {
+                                self.buf.next();
+                                return Ok(Token::Gt);
+                            }
+                            '+' => {
+                                self.buf.next();
+                                return Ok(Token::Plus);
+                            }
+                            '-' => {
+                                self.buf.next();
+                                return Ok(Token::Minus);
+                            }
+                            ':' => {
+                                self.buf.next();
+                                return Ok(Token::Colon);
+                            }
+                            '"' | '\'' => {
+                                return self.scan_string();
+                            }
+                            ch if ch.is_ascii_digit() => {
+                                return self.scan_number(String::new());
                             }
                             _ => {
-                                if escaped {
-                                    return err("Lexical error: unknown escaping");
-                                }
                                 token.push(c);
                                 self.buf.next();
                             }
                         }
                     } else {
-                        if token.len() == 0 {
-                            match c {
-                                '/' => {
-                                    self.unread_line_comment();
-                                    continue;
-                                }
-                                '{' => {
-                                    self.buf.next();
-                                    return Ok(Token::LCurly);
-                                }
-                                '}' => {
-                                    self.buf.next();
-                                    return Ok(Token::RCurly);
-                                }
-                                '=' => {
-                                    self.buf.next();
-                                    return Ok(Token::Eq);
-                                }
-                                '(' => {
-                                    self.buf.next();
-                                    return Ok(Token::LParen);
-                                }
-                                ')' => {
-                                    self.buf.next();
-                                    return Ok(Token::RParen);
-                                }
-                                ',' => {
-                                    self.buf.next();
-                                    return Ok(Token::Comma);
-                                }
-                                ';' => {
-                                    self.buf.next();
-                                    return Ok(Token::Semicolon);
-                                }
-                                '.' => {
-                                    self.buf.next();
-                                    return Ok(Token::Dot);
-                                }
-                                '[' => {
-                                    self.buf.next();
-                                    return Ok(Token::LBracket);
-                                }
-                                ']' => {
-                                    self.buf.next();
-                                    return Ok(Token::RBracket);
-                                }
-                                '<' => {
-                                    self.buf.next();
-                                    return Ok(Token::Lt);
-                                }
-                                '>' => {
-                                    self.buf.next();
-                                    return Ok(Token::Gt);
-                                }
-                                '+' => {
-                                    self.buf.next();
-                                    return Ok(Token::Plus);
-                                }
-                                '-' => {
-                                    self.buf.next();
-                                    return Ok(Token::Minus);
-                                }
-                                '"' => {
-                                    str_lit = true;
-                                    self.buf.next();
-                                    continue;
-                                }
-                                ch if ch.is_digit(10) => {
-                                    dec_lit = true;
-                                    token.push(c);
-                                    self.buf.next();
-                                }
-                                _ => {
-                                    token.push(c);
-                                    self.buf.next();
-                                }
-                            }
+                        if c.is_whitespace() || non_ident_char(c) {
+                            break;
                         } else {
-                            if c.is_whitespace() || non_ident_char(c) {
-                                break;
-                            } else {
-                                token.push(c);
-                                self.buf.next();
-                            }
+                            token.push(c);
+                            self.buf.next();
                         }
                     }
                 }
             }
         }
 
-        if dec_lit {
-            let dec = token.parse::<u32>();
-            match dec {
-                Err(_) => return err("Lexical error: illegal decimal literal"),
-                Ok(n) => return Ok(Token::DecimalLit(n)),
+        if let Some(lookup_token) = IDENT_MAP.get(&token[..]) {
+            return Ok(lookup_token.clone());
+        }
+        return Ok(Token::Ident(token));
+    }
+
+    //single or double quoted string literal, adjacent literals like
+    //"foo" 'bar' are concatenated into one. Hex and octal escapes produce
+    //raw bytes, if these don't form valid UTF-8 a BytesLit is returned
+    fn scan_string(&mut self) -> Result<Token, ProtoParseError> {
+        let mut bytes = Vec::new();
+        while let Some(quote) = self.buf.next_if(|c| *c == '"' || *c == '\'') {
+            loop {
+                match self.buf.next() {
+                    None => return lexical_err("unclosed string literal"),
+                    Some('\n') => return lexical_err("line break in string literal"),
+                    Some('\\') => self.scan_escape(&mut bytes)?,
+                    Some(c) if c == quote => break,
+                    Some(c) => {
+                        let mut utf8 = [0; 4];
+                        bytes.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+                    }
+                }
+            }
+            //the next part may follow after whitespace and comments, which
+            //are otherwise left for the trivia of the next token
+            let after_part = self.buf.clone();
+            self.skip_whitespace_and_comments();
+            if !matches!(self.buf.peek(), Some('"') | Some('\'')) {
+                self.buf = after_part;
+            }
+        }
+
+        return match String::from_utf8(bytes) {
+            Ok(s) => Ok(Token::StrLit(s)),
+            Err(e) => Ok(Token::BytesLit(e.into_bytes())),
+        };
+    }
+
+    //the escape sequence after a backslash
+    fn scan_escape(&mut self, bytes: &mut Vec<u8>) -> Result<(), ProtoParseError> {
+        let c = match self.buf.next() {
+            None => return lexical_err("unclosed string literal"),
+            Some(c) => c,
+        };
+        let byte = match c {
+            'a' => 0x07,
+            'b' => 0x08,
+            'f' => 0x0c,
+            'n' => b'\n',
+            'r' => b'\r',
+            't' => b'\t',
+            'v' => 0x0b,
+            '\\' => b'\\',
+            '\'' => b'\'',
+            '"' => b'"',
+            '?' => b'?',
+            'x' | 'X' => {
+                let hex = self.take_digits(2, 16);
+                if hex.is_empty() {
+                    return lexical_err("illegal hex escape");
+                }
+                u8::from_str_radix(&hex, 16).unwrap()
+            }
+            '0'..='7' => {
+                let mut oct = c.to_string();
+                oct.push_str(&self.take_digits(2, 8));
+                match u8::from_str_radix(&oct, 8) {
+                    Ok(b) => b,
+                    Err(_) => return lexical_err("octal escape out of range"),
+                }
+            }
+            'u' | 'U' => {
+                let ch = self.scan_unicode_escape(c)?;
+                let mut utf8 = [0; 4];
+                bytes.extend_from_slice(ch.encode_utf8(&mut utf8).as_bytes());
+                return Ok(());
+            }
+            _ => return lexical_err("unknown escaping"),
+        };
+        bytes.push(byte);
+        return Ok(());
+    }
+
+    //\uXXXX or \UXXXXXXXX, a \u high surrogate must be followed by a
+    //\u low surrogate
+    fn scan_unicode_escape(&mut self, kind: char) -> Result<char, ProtoParseError> {
+        let len = if kind == 'u' { 4 } else { 8 };
+        let hex = self.take_digits(len, 16);
+        if hex.len() != len {
+            return lexical_err("illegal unicode escape");
+        }
+        let mut code = u32::from_str_radix(&hex, 16).unwrap();
+        if kind == 'u' && (0xd800..0xdc00).contains(&code) {
+            if self.buf.next() != Some('\\') || self.buf.next() != Some('u') {
+                return lexical_err("unpaired surrogate in unicode escape");
+            }
+            let low_hex = self.take_digits(4, 16);
+            let low = u32::from_str_radix(&low_hex, 16).unwrap_or(0);
+            if low_hex.len() != 4 || !(0xdc00..0xe000).contains(&low) {
+                return lexical_err("unpaired surrogate in unicode escape");
+            }
+            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+        }
+        return match char::from_u32(code) {
+            Some(ch) => Ok(ch),
+            None => lexical_err("illegal unicode escape"),
+        };
+    }
+
+    fn take_digits(&mut self, max: usize, radix: u32) -> String {
+        let mut taken = String::new();
+        while taken.len() < max {
+            match self.buf.next_if(|c| c.is_digit(radix)) {
+                Some(c) => taken.push(c),
+                None => break,
             }
         }
+        return taken;
+    }
 
-        if str_lit {
-            return Ok(Token::StrLit(token));
+    //decimal, octal, hex and float literals. lit is "." if the literal
+    //started with a dot, the sign is scanned as a separate token
+    fn scan_number(&mut self, mut lit: String) -> Result<Token, ProtoParseError> {
+        let mut float = !lit.is_empty();
+
+        if lit.is_empty() && self.buf.peek() == Some(&'0') {
+            self.buf.next();
+            lit.push('0');
+            if let Some('x') | Some('X') = self.buf.peek() {
+                self.buf.next();
+                let hex = self.take_while(|c| c.is_ascii_hexdigit());
+                self.check_number_end()?;
+                if hex.is_empty() {
+                    return lexical_err("illegal hex literal");
+                }
+                return match u64::from_str_radix(&hex, 16) {
+                    Ok(n) => Ok(Token::IntLit(n)),
+                    Err(_) => lexical_err("integer literal out of range"),
+                };
+            }
         }
 
-        let lookup_token = IDENT_MAP.get(&token[..]);
-        if lookup_token.is_some() {
-            return Ok(lookup_token.unwrap().clone());
+        lit.push_str(&self.take_while(|c| c.is_ascii_digit()));
+        if !float && self.buf.peek() == Some(&'.') {
+            self.buf.next();
+            lit.push('.');
+            lit.push_str(&self.take_while(|c| c.is_ascii_digit()));
+            float = true;
         }
-        return Ok(Token::Ident(token));
+        if let Some('e') | Some('E') = self.buf.peek() {
+            self.buf.next();
+            lit.push('e');
+            if let Some(sign) = self.buf.next_if(|c| *c == '+' || *c == '-') {
+                lit.push(sign);
+            }
+            let exponent = self.take_while(|c| c.is_ascii_digit());
+            if exponent.is_empty() {
+                return lexical_err("illegal float literal, exponent expected");
+            }
+            lit.push_str(&exponent);
+            float = true;
+        }
+        self.check_number_end()?;
+
+        if float {
+            return match lit.parse::<f64>() {
+                Ok(f) => Ok(Token::FloatLit(f)),
+                Err(_) => lexical_err("illegal float literal"),
+            };
+        }
+
+        let parsed = if lit.len() > 1 && lit.starts_with('0') {
+            if lit.contains(['8', '9']) {
+                return lexical_err("illegal octal literal");
+            }
+            u64::from_str_radix(&lit[1..], 8)
+        } else {
+            lit.parse::<u64>()
+        };
+        return match parsed {
+            Ok(n) => Ok(Token::IntLit(n)),
+            Err(_) => lexical_err("integer literal out of range"),
+        };
+    }
+
+    //a number must not be directly followed by an identifier
+    fn check_number_end(&mut self) -> Result<(), ProtoParseError> {
+        match self.buf.peek() {
+            Some(c) if c.is_alphanumeric() || *c == '_' => {
+                return lexical_err("illegal number literal");
+            }
+            _ => return Ok(()),
+        }
+    }
+
+    fn take_while(&mut self, pred: fn(&char) -> bool) -> String {
+        let mut taken = String::new();
+        while let Some(c) = self.buf.next_if(pred) {
+            taken.push(c);
+        }
+        return taken;
     }
 
-    fn unread_line_comment(&mut self) {
+    //a // line or /* block */ comment, a lone / is an error. Line comments
+    //include the newline, block comments have the leading whitespace and *
+    //of their continuation lines removed.
+    fn read_comment(&mut self) -> Result<Option<Comment>, ProtoParseError> {
+        if self.buf.peek() != Some(&'/') {
+            return Ok(None);
+        }
+        self.record_trivia(TokenKind::Whitespace);
+        let (start, line, column) = (self.buf.offset, self.buf.line, self.buf.column);
+        self.buf.next();
+        let comment = if self.buf.next_if_eq(&'/').is_some() {
+            Ok(Comment::Line(self.read_line_comment()))
+        } else if self.buf.next_if_eq(&'*').is_some() {
+            self.read_block_comment().map(Comment::Block)
+        } else {
+            lexical_err("unexpected character '/'")
+        };
+        if comment.is_ok() {
+            self.record_trivia(TokenKind::Comment);
+        } else {
+            self.record_trivia(TokenKind::Error);
+        }
+        return comment
+            .map(Some)
+            .map_err(|e| e.or_span(Span::new(start, self.buf.offset, line, column)));
+    }
+
+    //adds the source up to the cursor to the cst
+    fn record_trivia(&mut self, kind: TokenKind) {
+        let end = self.buf.offset;
+        if let Some(cst) = &mut self.cst
+            && end > self.recorded
+        {
+            cst.trivia(kind, &self.source[self.recorded..end]);
+            self.recorded = end;
+        }
+    }
+
+    //adds the last token, which ends at end, to the cst
+    fn record_token(&mut self, end: usize) {
+        if self.cst.is_none() {
+            return;
+        }
+        let kind = match &self.token {
+            None => TokenKind::Error,
+            Some(Token::EOF) => return,
+            Some(Token::Ident(_)) => TokenKind::Ident,
+            Some(
+                Token::StrLit(_)
+                | Token::BytesLit(_)
+                | Token::IntLit(_)
+                | Token::FloatLit(_)
+                | Token::BoolLit(_),
+            ) => TokenKind::Literal,
+            Some(token) if token.keyword().is_some() => TokenKind::Keyword,
+            Some(_) => TokenKind::Symbol,
+        };
+        //a failed token spans all of the text scanned for it
+        let end = if kind == TokenKind::Error {
+            self.buf.offset
+        } else {
+            end
+        };
+        if let Some(cst) = &mut self.cst
+            && end > self.recorded
+        {
+            cst.token(kind, &self.source[self.recorded..end]);
+            self.recorded = end;
+        }
+        self.record_trivia(TokenKind::Whitespace);
+    }
+
+    fn read_line_comment(&mut self) -> String {
+        let mut text = String::new();
+        for c in self.buf.by_ref() {
+            text.push(c);
+            if c == '\n' {
+                break;
+            }
+        }
+        return text;
+    }
+
+    fn read_block_comment(&mut self) -> Result<String, ProtoParseError> {
+        let mut text = String::new();
         loop {
-            let peek = self.buf.peek().map(|c| *c);
-            match peek {
-                None => return,
-                Some(c) => {
-                    self.buf.next();
-                    if c == '\n' {
-                        self.unread_whitespace();
+            match self.buf.next() {
+                None => return lexical_err("unterminated block comment"),
+                Some('*') if self.buf.next_if_eq(&'/').is_some() => return Ok(text),
+                Some('\n') => {
+                    text.push('\n');
+                    self.unread_blank();
+                    if self.buf.next_if_eq(&'*').is_some() && self.buf.next_if_eq(&'/').is_some() {
+                        return Ok(text);
+                    }
+                }
+                Some(c) => text.push(c),
+            }
+        }
+    }
+
+    //stops at a lone / and at the end of an unterminated block comment
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            self.unread_whitespace();
+            let mut ahead = self.buf.clone();
+            if ahead.next() != Some('/') {
+                return;
+            }
+            match ahead.next() {
+                Some('/') => {
+                    self.buf = ahead;
+                    self.read_line_comment();
+                }
+                Some('*') => {
+                    self.buf = ahead;
+                    if self.read_block_comment().is_err() {
                         return;
                     }
                 }
+                _ => return,
             }
         }
     }
 
+    //whitespace up to the next line break
+    fn unread_blank(&mut self) {
+        while self
+            .buf
+            .next_if(|c| c.is_whitespace() && *c != '\n')
+            .is_some()
+        {}
+    }
+
     fn unread_whitespace(&mut self) {
         loop {
-            let peek = self.buf.peek().map(|c| *c);
+            let peek = self.buf.peek().copied();
             match peek {
                 None => return, //caller will detect EOF
                 Some(c) => {
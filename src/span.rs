@@ -0,0 +1,38 @@
+//location in the source, start and end are byte offsets (end exclusive),
+//line and column (both 1-based, column in chars) are those of start
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Span {
+        return Span {
+            start,
+            end,
+            line,
+            column,
+        };
+    }
+
+    //span from the start of self to the end of other
+    pub fn to(&self, other: Span) -> Span {
+        return Span {
+            start: self.start,
+            end: other.end,
+            line: self.line,
+            column: self.column,
+        };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.end - self.start;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.start == self.end;
+    }
+}
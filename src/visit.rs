@@ -0,0 +1,598 @@
+//traversal of the ast. Visit and VisitMut have a method for each node type
+//that calls the walk function of the same name by default, which visits the
+//children of the node grouped by kind in the order of the struct fields,
+//e.g. for a message the options, reserved, extensions, fields, oneofs,
+//nested messages, enums and extends. Each group is in declaration order, sort
+//by span to interleave them as in the source. Overriding a method replaces
+//the traversal below that node, call the walk function to keep it.
+//Group messages are visited as nested messages of the scope declaring them.
+//
+//e.g. counting the fields of all messages, including nested ones:
+//
+//struct FieldCount(usize);
+//
+//impl<'ast> Visit<'ast> for FieldCount {
+//    fn visit_field(&mut self, node: &'ast Field) {
+//        self.0 += 1;
+//        visit_field(self, node);
+//    }
+//}
+
+use super::ast::*;
+
+pub trait Visit<'ast> {
+    fn visit_proto_def(&mut self, node: &'ast ProtoDef) {
+        visit_proto_def(self, node);
+    }
+
+    fn visit_comments(&mut self, node: &'ast Comments) {
+        visit_comments(self, node);
+    }
+
+    fn visit_import(&mut self, node: &'ast Import) {
+        visit_import(self, node);
+    }
+
+    fn visit_package(&mut self, node: &'ast Package) {
+        visit_package(self, node);
+    }
+
+    fn visit_full_ident(&mut self, node: &'ast FullIdent) {
+        visit_full_ident(self, node);
+    }
+
+    fn visit_option(&mut self, node: &'ast ProtoOption) {
+        visit_option(self, node);
+    }
+
+    fn visit_constant_value(&mut self, node: &'ast ConstantValue) {
+        visit_constant_value(self, node);
+    }
+
+    fn visit_aggregate_field(&mut self, node: &'ast AggregateField) {
+        visit_aggregate_field(self, node);
+    }
+
+    fn visit_service(&mut self, node: &'ast Service) {
+        visit_service(self, node);
+    }
+
+    fn visit_rpc(&mut self, node: &'ast Rpc) {
+        visit_rpc(self, node);
+    }
+
+    fn visit_message(&mut self, node: &'ast Message) {
+        visit_message(self, node);
+    }
+
+    fn visit_extensions(&mut self, node: &'ast Extensions) {
+        visit_extensions(self, node);
+    }
+
+    fn visit_extension_range(&mut self, node: &'ast ExtensionRange) {
+        visit_extension_range(self, node);
+    }
+
+    fn visit_extend(&mut self, node: &'ast Extend) {
+        visit_extend(self, node);
+    }
+
+    fn visit_oneof(&mut self, node: &'ast OneOf) {
+        visit_oneof(self, node);
+    }
+
+    fn visit_field(&mut self, node: &'ast Field) {
+        visit_field(self, node);
+    }
+
+    fn visit_type(&mut self, node: &'ast Type) {
+        visit_type(self, node);
+    }
+
+    fn visit_enum(&mut self, node: &'ast Enum) {
+        visit_enum(self, node);
+    }
+
+    fn visit_enum_value(&mut self, node: &'ast EnumValue) {
+        visit_enum_value(self, node);
+    }
+
+    fn visit_reserved(&mut self, node: &'ast Reserved) {
+        visit_reserved(self, node);
+    }
+
+    fn visit_reserved_range(&mut self, node: &'ast ReservedRange) {
+        visit_reserved_range(self, node);
+    }
+}
+
+pub fn visit_proto_def<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ProtoDef) {
+    v.visit_comments(&node.comments);
+    for import in node.imports.iter() {
+        v.visit_import(import);
+    }
+    for package in node.packages.iter() {
+        v.visit_package(package);
+    }
+    for option in node.options.iter() {
+        v.visit_option(option);
+    }
+    for service in node.services.iter() {
+        v.visit_service(service);
+    }
+    for message in node.messages.iter() {
+        v.visit_message(message);
+    }
+    for enum_def in node.enums.iter() {
+        v.visit_enum(enum_def);
+    }
+    for extend in node.extends.iter() {
+        v.visit_extend(extend);
+    }
+}
+
+pub fn visit_comments<'ast, V: Visit<'ast> + ?Sized>(_v: &mut V, _node: &'ast Comments) {}
+
+pub fn visit_import<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Import) {
+    v.visit_comments(&node.comments);
+}
+
+pub fn visit_package<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Package) {
+    v.visit_comments(&node.comments);
+    v.visit_full_ident(&node.full_ident);
+}
+
+pub fn visit_full_ident<'ast, V: Visit<'ast> + ?Sized>(_v: &mut V, _node: &'ast FullIdent) {}
+
+pub fn visit_option<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ProtoOption) {
+    v.visit_comments(&node.comments);
+    for part in node.name.iter() {
+        v.visit_full_ident(&part.name);
+    }
+    v.visit_constant_value(&node.constant);
+}
+
+pub fn visit_constant_value<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast ConstantValue) {
+    match node {
+        ConstantValue::IdentValue(ident) => v.visit_full_ident(ident),
+        ConstantValue::AggregateValue(fields) => {
+            for field in fields.iter() {
+                v.visit_aggregate_field(field);
+            }
+        }
+        ConstantValue::ListValue(values) => {
+            for value in values.iter() {
+                v.visit_constant_value(value);
+            }
+        }
+        ConstantValue::IntValue(_)
+        | ConstantValue::FloatValue(_)
+        | ConstantValue::StringValue(_)
+        | ConstantValue::BytesValue(_)
+        | ConstantValue::BoolValue(_) => {}
+    }
+}
+
+pub fn visit_aggregate_field<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast AggregateField) {
+    v.visit_full_ident(&node.name);
+    v.visit_constant_value(&node.value);
+}
+
+pub fn visit_service<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Service) {
+    v.visit_comments(&node.comments);
+    for option in node.options.iter() {
+        v.visit_option(option);
+    }
+    for rpc in node.rpcs.iter() {
+        v.visit_rpc(rpc);
+    }
+}
+
+pub fn visit_rpc<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Rpc) {
+    v.visit_comments(&node.comments);
+    v.visit_full_ident(&node.request_type);
+    v.visit_full_ident(&node.response_type);
+    for option in node.options.iter() {
+        v.visit_option(option);
+    }
+}
+
+pub fn visit_message<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Message) {
+    v.visit_comments(&node.comments);
+    for option in node.options.iter() {
+        v.visit_option(option);
+    }
+    for reserved in node.reserved.iter() {
+        v.visit_reserved(reserved);
+    }
+    for extensions in node.extensions.iter() {
+        v.visit_extensions(extensions);
+    }
+    for field in node.fields.iter() {
+        v.visit_field(field);
+    }
+    for oneof in node.oneofs.iter() {
+        v.visit_oneof(oneof);
+    }
+    for message in node.nested_messages.iter() {
+        v.visit_message(message);
+    }
+    for enum_def in node.nested_enums.iter() {
+        v.visit_enum(enum_def);
+    }
+    for extend in node.extends.iter() {
+        v.visit_extend(extend);
+    }
+}
+
+pub fn visit_extensions<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Extensions) {
+    v.visit_comments(&node.comments);
+    for range in node.ranges.iter() {
+        v.visit_extension_range(range);
+    }
+    for option in node.options.iter() {
+        v.visit_option(option);
+    }
+}
+
+pub fn visit_extension_range<'ast, V: Visit<'ast> + ?Sized>(
+    _v: &mut V,
+    _node: &'ast ExtensionRange,
+) {
+}
+
+pub fn visit_extend<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Extend) {
+    v.visit_comments(&node.comments);
+    v.visit_full_ident(&node.extendee);
+    for field in node.fields.iter() {
+        v.visit_field(field);
+    }
+}
+
+pub fn visit_oneof<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast OneOf) {
+    v.visit_comments(&node.comments);
+    for option in node.options.iter() {
+        v.visit_option(option);
+    }
+    for field in node.fields.iter() {
+        v.visit_field(field);
+    }
+}
+
+pub fn visit_field<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Field) {
+    v.visit_comments(&node.comments);
+    v.visit_type(&node.field_type);
+    if let Some(default) = &node.default {
+        v.visit_constant_value(default);
+    }
+    for option in node.options.iter() {
+        v.visit_option(option);
+    }
+}
+
+pub fn visit_type<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Type) {
+    match node {
+        Type::Reference { full_ident, .. } => v.visit_full_ident(full_ident),
+        Type::Map { key, value } => {
+            v.visit_type(key);
+            v.visit_type(value);
+        }
+        _ => {}
+    }
+}
+
+pub fn visit_enum<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Enum) {
+    v.visit_comments(&node.comments);
+    for option in node.options.iter() {
+        v.visit_option(option);
+    }
+    for reserved in node.reserved.iter() {
+        v.visit_reserved(reserved);
+    }
+    for value in node.values.iter() {
+        v.visit_enum_value(value);
+    }
+}
+
+pub fn visit_enum_value<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast EnumValue) {
+    v.visit_comments(&node.comments);
+    for option in node.options.iter() {
+        v.visit_option(option);
+    }
+}
+
+pub fn visit_reserved<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Reserved) {
+    v.visit_comments(&node.comments);
+    for range in node.ranges.iter() {
+        v.visit_reserved_range(range);
+    }
+}
+
+pub fn visit_reserved_range<'ast, V: Visit<'ast> + ?Sized>(_v: &mut V, _node: &'ast ReservedRange) {
+}
+
+pub trait VisitMut {
+    fn visit_proto_def_mut(&mut self, node: &mut ProtoDef) {
+        visit_proto_def_mut(self, node);
+    }
+
+    fn visit_comments_mut(&mut self, node: &mut Comments) {
+        visit_comments_mut(self, node);
+    }
+
+    fn visit_import_mut(&mut self, node: &mut Import) {
+        visit_import_mut(self, node);
+    }
+
+    fn visit_package_mut(&mut self, node: &mut Package) {
+        visit_package_mut(self, node);
+    }
+
+    fn visit_full_ident_mut(&mut self, node: &mut FullIdent) {
+        visit_full_ident_mut(self, node);
+    }
+
+    fn visit_option_mut(&mut self, node: &mut ProtoOption) {
+        visit_option_mut(self, node);
+    }
+
+    fn visit_constant_value_mut(&mut self, node: &mut ConstantValue) {
+        visit_constant_value_mut(self, node);
+    }
+
+    fn visit_aggregate_field_mut(&mut self, node: &mut AggregateField) {
+        visit_aggregate_field_mut(self, node);
+    }
+
+    fn visit_service_mut(&mut self, node: &mut Service) {
+        visit_service_mut(self, node);
+    }
+
+    fn visit_rpc_mut(&mut self, node: &mut Rpc) {
+        visit_rpc_mut(self, node);
+    }
+
+    fn visit_message_mut(&mut self, node: &mut Message) {
+        visit_message_mut(self, node);
+    }
+
+    fn visit_extensions_mut(&mut self, node: &mut Extensions) {
+        visit_extensions_mut(self, node);
+    }
+
+    fn visit_extension_range_mut(&mut self, node: &mut ExtensionRange) {
+        visit_extension_range_mut(self, node);
+    }
+
+    fn visit_extend_mut(&mut self, node: &mut Extend) {
+        visit_extend_mut(self, node);
+    }
+
+    fn visit_oneof_mut(&mut self, node: &mut OneOf) {
+        visit_oneof_mut(self, node);
+    }
+
+    fn visit_field_mut(&mut self, node: &mut Field) {
+        visit_field_mut(self, node);
+    }
+
+    fn visit_type_mut(&mut self, node: &mut Type) {
+        visit_type_mut(self, node);
+    }
+
+    fn visit_enum_mut(&mut self, node: &mut Enum) {
+        visit_enum_mut(self, node);
+    }
+
+    fn visit_enum_value_mut(&mut self, node: &mut EnumValue) {
+        visit_enum_value_mut(self, node);
+    }
+
+    fn visit_reserved_mut(&mut self, node: &mut Reserved) {
+        visit_reserved_mut(self, node);
+    }
+
+    fn visit_reserved_range_mut(&mut self, node: &mut ReservedRange) {
+        visit_reserved_range_mut(self, node);
+    }
+}
+
+pub fn visit_proto_def_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ProtoDef) {
+    v.visit_comments_mut(&mut node.comments);
+    for import in node.imports.iter_mut() {
+        v.visit_import_mut(import);
+    }
+    for package in node.packages.iter_mut() {
+        v.visit_package_mut(package);
+    }
+    for option in node.options.iter_mut() {
+        v.visit_option_mut(option);
+    }
+    for service in node.services.iter_mut() {
+        v.visit_service_mut(service);
+    }
+    for message in node.messages.iter_mut() {
+        v.visit_message_mut(message);
+    }
+    for enum_def in node.enums.iter_mut() {
+        v.visit_enum_mut(enum_def);
+    }
+    for extend in node.extends.iter_mut() {
+        v.visit_extend_mut(extend);
+    }
+}
+
+pub fn visit_comments_mut<V: VisitMut + ?Sized>(_v: &mut V, _node: &mut Comments) {}
+
+pub fn visit_import_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Import) {
+    v.visit_comments_mut(&mut node.comments);
+}
+
+pub fn visit_package_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Package) {
+    v.visit_comments_mut(&mut node.comments);
+    v.visit_full_ident_mut(&mut node.full_ident);
+}
+
+pub fn visit_full_ident_mut<V: VisitMut + ?Sized>(_v: &mut V, _node: &mut FullIdent) {}
+
+pub fn visit_option_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ProtoOption) {
+    v.visit_comments_mut(&mut node.comments);
+    for part in node.name.iter_mut() {
+        v.visit_full_ident_mut(&mut part.name);
+    }
+    v.visit_constant_value_mut(&mut node.constant);
+}
+
+pub fn visit_constant_value_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut ConstantValue) {
+    match node {
+        ConstantValue::IdentValue(ident) => v.visit_full_ident_mut(ident),
+        ConstantValue::AggregateValue(fields) => {
+            for field in fields.iter_mut() {
+                v.visit_aggregate_field_mut(field);
+            }
+        }
+        ConstantValue::ListValue(values) => {
+            for value in values.iter_mut() {
+                v.visit_constant_value_mut(value);
+            }
+        }
+        ConstantValue::IntValue(_)
+        | ConstantValue::FloatValue(_)
+        | ConstantValue::StringValue(_)
+        | ConstantValue::BytesValue(_)
+        | ConstantValue::BoolValue(_) => {}
+    }
+}
+
+pub fn visit_aggregate_field_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut AggregateField) {
+    v.visit_full_ident_mut(&mut node.name);
+    v.visit_constant_value_mut(&mut node.value);
+}
+
+pub fn visit_service_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Service) {
+    v.visit_comments_mut(&mut node.comments);
+    for option in node.options.iter_mut() {
+        v.visit_option_mut(option);
+    }
+    for rpc in node.rpcs.iter_mut() {
+        v.visit_rpc_mut(rpc);
+    }
+}
+
+pub fn visit_rpc_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Rpc) {
+    v.visit_comments_mut(&mut node.comments);
+    v.visit_full_ident_mut(&mut node.request_type);
+    v.visit_full_ident_mut(&mut node.response_type);
+    for option in node.options.iter_mut() {
+        v.visit_option_mut(option);
+    }
+}
+
+pub fn visit_message_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Message) {
+    v.visit_comments_mut(&mut node.comments);
+    for option in node.options.iter_mut() {
+        v.visit_option_mut(option);
+    }
+    for reserved in node.reserved.iter_mut() {
+        v.visit_reserved_mut(reserved);
+    }
+    for extensions in node.extensions.iter_mut() {
+        v.visit_extensions_mut(extensions);
+    }
+    for field in node.fields.iter_mut() {
+        v.visit_field_mut(field);
+    }
+    for oneof in node.oneofs.iter_mut() {
+        v.visit_oneof_mut(oneof);
+    }
+    for message in node.nested_messages.iter_mut() {
+        v.visit_message_mut(message);
+    }
+    for enum_def in node.nested_enums.iter_mut() {
+        v.visit_enum_mut(enum_def);
+    }
+    for extend in node.extends.iter_mut() {
+        v.visit_extend_mut(extend);
+    }
+}
+
+pub fn visit_extensions_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Extensions) {
+    v.visit_comments_mut(&mut node.comments);
+    for range in node.ranges.iter_mut() {
+        v.visit_extension_range_mut(range);
+    }
+    for option in node.options.iter_mut() {
+        v.visit_option_mut(option);
+    }
+}
+
+pub fn visit_extension_range_mut<V: VisitMut + ?Sized>(_v: &mut V, _node: &mut ExtensionRange) {}
+
+pub fn visit_extend_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Extend) {
+    v.visit_comments_mut(&mut node.comments);
+    v.visit_full_ident_mut(&mut node.extendee);
+    for field in node.fields.iter_mut() {
+        v.visit_field_mut(field);
+    }
+}
+
+pub fn visit_oneof_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut OneOf) {
+    v.visit_comments_mut(&mut node.comments);
+    for option in node.options.iter_mut() {
+        v.visit_option_mut(option);
+    }
+    for field in node.fields.iter_mut() {
+        v.visit_field_mut(field);
+    }
+}
+
+pub fn visit_field_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Field) {
+    v.visit_comments_mut(&mut node.comments);
+    v.visit_type_mut(&mut node.field_type);
+    if let Some(default) = &mut node.default {
+        v.visit_constant_value_mut(default);
+    }
+    for option in node.options.iter_mut() {
+        v.visit_option_mut(option);
+    }
+}
+
+pub fn visit_type_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Type) {
+    match node {
+        Type::Reference { full_ident, .. } => v.visit_full_ident_mut(full_ident),
+        Type::Map { key, value } => {
+            v.visit_type_mut(key);
+            v.visit_type_mut(value);
+        }
+        _ => {}
+    }
+}
+
+pub fn visit_enum_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Enum) {
+    v.visit_comments_mut(&mut node.comments);
+    for option in node.options.iter_mut() {
+        v.visit_option_mut(option);
+    }
+    for reserved in node.reserved.iter_mut() {
+        v.visit_reserved_mut(reserved);
+    }
+    for value in node.values.iter_mut() {
+        v.visit_enum_value_mut(value);
+    }
+}
+
+pub fn visit_enum_value_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut EnumValue) {
+    v.visit_comments_mut(&mut node.comments);
+    for option in node.options.iter_mut() {
+        v.visit_option_mut(option);
+    }
+}
+
+pub fn visit_reserved_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Reserved) {
+    v.visit_comments_mut(&mut node.comments);
+    for range in node.ranges.iter_mut() {
+        v.visit_reserved_range_mut(range);
+    }
+}
+
+pub fn visit_reserved_range_mut<V: VisitMut + ?Sized>(_v: &mut V, _node: &mut ReservedRange) {}
@@ -1,6 +1,7 @@
 extern crate protoparse;
 
 use protoparse::ast::*;
+use protoparse::span::Span;
 
 #[test]
 fn should_insert_into_full_ident() {
@@ -16,6 +17,8 @@ fn should_add_import() {
     def.add_import(Import {
         import_type: ImportType::Default,
         name: "test".to_string(),
+        span: Span::default(),
+        comments: Comments::default(),
     });
     assert_eq!(def.imports.len(), 1);
     assert_eq!(def.imports[0].name, "test".to_string());
@@ -23,6 +26,8 @@ fn should_add_import() {
     def.add_import(Import {
         import_type: ImportType::Weak,
         name: "test2".to_string(),
+        span: Span::default(),
+        comments: Comments::default(),
     });
     assert_eq!(def.imports.len(), 2);
     assert_eq!(def.imports[0].name, "test".to_string());
@@ -35,10 +40,14 @@ fn should_add_package() {
     assert_eq!(def.packages.len(), 0);
     def.add_package(Package {
         full_ident: FullIdent::new(vec!["foo".to_string()]),
+        span: Span::default(),
+        comments: Comments::default(),
     });
     assert_eq!(def.packages.len(), 1);
     def.add_package(Package {
         full_ident: FullIdent::new(vec!["foo".to_string(), "bar".to_string()]),
+        span: Span::default(),
+        comments: Comments::default(),
     });
     assert_eq!(def.packages.len(), 2);
 
@@ -50,15 +59,71 @@ fn should_add_option() {
     let mut def = ProtoDef::new(Syntax::V3);
     assert_eq!(def.options.len(), 0);
     def.add_option(ProtoOption {
-        full_ident: FullIdent::new(vec!["foo".to_string()]),
+        name: vec![OptionNamePart::new("foo")],
         constant: ConstantValue::BoolValue(true),
+        span: Span::default(),
+        comments: Comments::default(),
     });
     assert_eq!(def.options.len(), 1);
     def.add_option(ProtoOption {
-        full_ident: FullIdent::new(vec!["bar".to_string()]),
-        constant: ConstantValue::NumberValue(666.0),
+        name: vec![OptionNamePart::new("bar")],
+        constant: ConstantValue::IntValue(666),
+        span: Span::default(),
+        comments: Comments::default(),
     });
     assert_eq!(def.options.len(), 2);
 
-    assert_eq!(def.options[1].constant, ConstantValue::NumberValue(666.0));
+    assert_eq!(def.options[1].constant, ConstantValue::IntValue(666));
+}
+
+#[test]
+fn should_add_enum() {
+    let mut def = ProtoDef::new(Syntax::V3);
+    assert_eq!(def.enums.len(), 0);
+    def.add_enum(Enum {
+        name: "Foo".to_string(),
+        values: vec![EnumValue {
+            name: "FOO_UNSPECIFIED".to_string(),
+            number: 0,
+            options: Vec::new(),
+            span: Span::default(),
+            comments: Comments::default(),
+        }],
+        reserved: Vec::new(),
+        options: Vec::new(),
+        span: Span::default(),
+        comments: Comments::default(),
+    });
+    assert_eq!(def.enums.len(), 1);
+    assert_eq!(def.enums[0].values[0].number, 0);
+}
+
+#[test]
+fn should_check_reserved_numbers_and_names() {
+    let range = |start, end| ReservedRange {
+        start,
+        end,
+        span: Span::default(),
+    };
+    let ranges = Reserved {
+        ranges: vec![range(2, 2), range(9, 11)],
+        names: Vec::new(),
+        span: Span::default(),
+        comments: Comments::default(),
+    };
+    assert!(ranges.contains_number(2));
+    assert!(ranges.contains_number(9));
+    assert!(ranges.contains_number(11));
+    assert!(!ranges.contains_number(12));
+    assert!(!ranges.contains_name("foo"));
+
+    let names = Reserved {
+        ranges: Vec::new(),
+        names: vec!["foo".to_string(), "bar".to_string()],
+        span: Span::default(),
+        comments: Comments::default(),
+    };
+    assert!(names.contains_name("bar"));
+    assert!(!names.contains_name("baz"));
+    assert!(!names.contains_number(2));
 }
@@ -0,0 +1,178 @@
+extern crate protoparse;
+
+use std::fs;
+
+use protoparse::cst::*;
+use protoparse::parser::parse_cst;
+use protoparse::scanner::{Scanner, Token};
+use protoparse::span::Span;
+
+#[test]
+fn should_print_unmodified_tree_as_input() {
+    let input = "// header\r\n\nsyntax = \"proto3\";  /* a */\n\npackage  foo . bar;\n\
+        message Foo {\n\t// comment\n  map<string, int32> m = 1 [deprecated = true]; // x\n\
+        \toneof o { string s = 2; }\n  option (my.opt) = { a: 1 b: [\"x\" 'y'] };\n}\n\
+        enum E { A = 0; reserved 2 to 5, 9; }\nservice S { rpc R (stream Foo) returns (Foo); }\n"
+        .to_string();
+
+    let (tree, _, diagnostics) = parse_cst(&input);
+
+    assert!(diagnostics.is_empty());
+    assert_eq!(tree.to_string(), input);
+}
+
+#[test]
+fn should_print_testdata_files_unchanged() {
+    for entry in fs::read_dir("tests/testdata").unwrap() {
+        let input = fs::read_to_string(entry.unwrap().path()).unwrap();
+        let (tree, _, _) = parse_cst(&input);
+        assert_eq!(tree.to_string(), input);
+    }
+}
+
+#[test]
+fn should_keep_invalid_input() {
+    let inputs = [
+        "message Foo { string s = 1 }\nmessage Bar {",
+        "syntax = \"proto3\";\nmessage Foo { string s = \"unterminated\n}",
+        "message Foo { } / enum",
+        "message /* unterminated",
+        "enum E { A = 0; } }}} 1.5e+ ;",
+        "\"a\" \n \"b\" ;;",
+        "",
+    ];
+    for input in inputs {
+        let (tree, _, _) = parse_cst(input);
+        assert_eq!(tree.to_string(), input);
+    }
+}
+
+#[test]
+fn should_build_nodes_for_statements() {
+    let input =
+        "syntax = \"proto3\";\n// foo\nmessage Foo {\n  int32 a = 1;\n  enum E { X = 0; }\n}\n";
+
+    let (tree, _, _) = parse_cst(input);
+
+    assert_eq!(tree.kind, NodeKind::File);
+    let kinds: Vec<NodeKind> = tree.nodes().map(|n| n.kind).collect();
+    assert_eq!(kinds, [NodeKind::Syntax, NodeKind::Message]);
+
+    let message = tree.nodes().nth(1).unwrap();
+    assert_eq!(
+        message.to_string(),
+        "message Foo {\n  int32 a = 1;\n  enum E { X = 0; }\n}"
+    );
+    let kinds: Vec<NodeKind> = message.nodes().map(|n| n.kind).collect();
+    assert_eq!(kinds, [NodeKind::Field, NodeKind::Enum]);
+    assert_eq!(message.nodes().next().unwrap().to_string(), "int32 a = 1;");
+
+    //the comment is between the statements
+    let comment = tree
+        .children
+        .iter()
+        .find_map(|c| match c {
+            Element::Token(t) if t.kind == TokenKind::Comment => Some(t),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(comment.text, "// foo\n");
+}
+
+#[test]
+fn should_classify_tokens() {
+    let (tree, _, _) = parse_cst("option foo = true;");
+
+    let tokens: Vec<(TokenKind, &str)> = tree
+        .tokens()
+        .into_iter()
+        .map(|t| (t.kind, t.text.as_str()))
+        .collect();
+    assert_eq!(
+        tokens,
+        [
+            (TokenKind::Keyword, "option"),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::Ident, "foo"),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::Symbol, "="),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::Literal, "true"),
+            (TokenKind::Symbol, ";"),
+        ]
+    );
+}
+
+#[test]
+fn should_put_failed_statements_in_error_nodes() {
+    let input = "enum E {\n  = 1;\n  A = 0;\n}";
+
+    let (tree, def, diagnostics) = parse_cst(input);
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(def.enums[0].values.len(), 1);
+    let enum_node = tree.nodes().next().unwrap();
+    let nodes: Vec<(NodeKind, String)> =
+        enum_node.nodes().map(|n| (n.kind, n.to_string())).collect();
+    assert_eq!(
+        nodes,
+        [
+            (NodeKind::Error, "= 1;".to_string()),
+            (NodeKind::EnumValue, "A = 0;".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn should_give_nodes_and_tokens_spans() {
+    let input = "syntax = \"proto3\";\nmessage Foo {\n  int32 a = 1;\n}\n";
+
+    let (tree, _, _) = parse_cst(input);
+
+    assert_eq!(tree.span, Span::new(0, input.len(), 1, 1));
+    for token in tree.tokens() {
+        assert_eq!(&input[token.span.start..token.span.end], token.text);
+    }
+    let field = tree.nodes().nth(1).unwrap().nodes().next().unwrap();
+    assert_eq!(field.span, Span::new(35, 47, 3, 3));
+    let ident = field.tokens()[2];
+    assert_eq!(ident.text, "a");
+    assert_eq!(ident.span, Span::new(41, 42, 3, 9));
+}
+
+#[test]
+fn should_find_node_of_ast_node() {
+    let input =
+        "syntax = \"proto3\";\nmessage Foo {\n  int32 a = 1;\n  message Bar { int32 a = 1; }\n}\n";
+    let (mut tree, def, _) = parse_cst(input);
+    let field = &def.messages[0].nested_messages[0].fields[0];
+
+    let node = tree.find(NodeKind::Field, field.span).unwrap();
+    assert_eq!(node.to_string(), "int32 a = 1;");
+    assert_eq!(node.span, field.span);
+    assert!(tree.find(NodeKind::Message, field.span).is_none());
+
+    let node = tree.find_mut(NodeKind::Field, field.span).unwrap();
+    for child in node.children.iter_mut() {
+        if let Element::Token(token) = child
+            && token.text == "a"
+        {
+            token.text = "b".to_string();
+        }
+    }
+    assert_eq!(
+        tree.to_string(),
+        "syntax = \"proto3\";\nmessage Foo {\n  int32 a = 1;\n  message Bar { int32 b = 1; }\n}\n"
+    );
+}
+
+#[test]
+fn should_build_tree_only_when_asked() {
+    let input = "message Foo {}";
+
+    assert!(Scanner::new(input).finish_cst().is_none());
+
+    let mut scanner = Scanner::with_cst(input);
+    while scanner.next_token() != Ok(Token::EOF) {}
+    assert_eq!(scanner.finish_cst().unwrap().to_string(), input);
+}
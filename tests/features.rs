@@ -0,0 +1,195 @@
+extern crate protoparse;
+
+use protoparse::features::*;
+use protoparse::parser::parse;
+
+#[test]
+fn should_resolve_edition_defaults() {
+    let input = "edition = \"2023\"; message M { int32 a = 1; }".to_string();
+    let def = parse(&input).unwrap();
+
+    let file = ResolvedFeatures::for_file(&def).unwrap();
+    let field = file
+        .for_message(&def.messages[0])
+        .unwrap()
+        .for_field(&def.messages[0].fields[0])
+        .unwrap();
+    assert_eq!(field.field_presence, FieldPresence::Explicit);
+    assert_eq!(field.enum_type, EnumType::Open);
+    assert_eq!(field.repeated_field_encoding, RepeatedFieldEncoding::Packed);
+}
+
+#[test]
+fn should_resolve_inherited_field_presence() {
+    let input = r#"edition = "2023";
+    option features.field_presence = IMPLICIT;
+    message Outer {
+      int32 implicit = 1;
+      message Inner {
+        option features.field_presence = EXPLICIT;
+        int32 explicit = 1;
+        int32 required = 2 [features.field_presence = LEGACY_REQUIRED];
+      }
+    }"#
+    .to_string();
+    let def = parse(&input).unwrap();
+
+    let file = ResolvedFeatures::for_file(&def).unwrap();
+    let outer = &def.messages[0];
+    let outer_features = file.for_message(outer).unwrap();
+    assert_eq!(
+        outer_features
+            .for_field(&outer.fields[0])
+            .unwrap()
+            .field_presence,
+        FieldPresence::Implicit
+    );
+
+    let inner = &outer.nested_messages[0];
+    let inner_features = outer_features.for_message(inner).unwrap();
+    assert_eq!(
+        inner_features
+            .for_field(&inner.fields[0])
+            .unwrap()
+            .field_presence,
+        FieldPresence::Explicit
+    );
+    assert_eq!(
+        inner_features
+            .for_field(&inner.fields[1])
+            .unwrap()
+            .field_presence,
+        FieldPresence::LegacyRequired
+    );
+}
+
+#[test]
+fn should_resolve_enum_openness() {
+    let input = r#"edition = "2023";
+    enum Open { OPEN_UNKNOWN = 0; }
+    enum Closed {
+      option features = { enum_type: CLOSED };
+      CLOSED_UNKNOWN = 0;
+    }"#
+    .to_string();
+    let def = parse(&input).unwrap();
+
+    let file = ResolvedFeatures::for_file(&def).unwrap();
+    assert_eq!(
+        file.for_enum(&def.enums[0]).unwrap().enum_type,
+        EnumType::Open
+    );
+    assert_eq!(
+        file.for_enum(&def.enums[1]).unwrap().enum_type,
+        EnumType::Closed
+    );
+}
+
+#[test]
+fn should_resolve_proto2_and_proto3_like_editions() {
+    let input = "syntax = \"proto2\"; message M { required int32 a = 1; optional int32 b = 2; }"
+        .to_string();
+    let def = parse(&input).unwrap();
+    let message = ResolvedFeatures::for_file(&def)
+        .unwrap()
+        .for_message(&def.messages[0])
+        .unwrap();
+    assert_eq!(message.enum_type, EnumType::Closed);
+    assert_eq!(
+        message
+            .for_field(&def.messages[0].fields[0])
+            .unwrap()
+            .field_presence,
+        FieldPresence::LegacyRequired
+    );
+    assert_eq!(
+        message
+            .for_field(&def.messages[0].fields[1])
+            .unwrap()
+            .field_presence,
+        FieldPresence::Explicit
+    );
+
+    let input = "syntax = \"proto3\"; message M { int32 a = 1; }".to_string();
+    let def = parse(&input).unwrap();
+    let file = ResolvedFeatures::for_file(&def).unwrap();
+    assert_eq!(file.field_presence, FieldPresence::Implicit);
+    assert_eq!(file.enum_type, EnumType::Open);
+}
+
+#[test]
+fn should_resolve_packed_option_and_oneof_presence() {
+    let input = "syntax = \"proto3\";
+    message M {
+      repeated int32 expanded = 1 [packed = false];
+      repeated int32 packed = 2;
+      oneof o { int32 member = 3; }
+    }"
+    .to_string();
+    let def = parse(&input).unwrap();
+    let message = &def.messages[0];
+    let features = ResolvedFeatures::for_file(&def)
+        .unwrap()
+        .for_message(message)
+        .unwrap();
+    assert_eq!(
+        features
+            .for_field(&message.fields[0])
+            .unwrap()
+            .repeated_field_encoding,
+        RepeatedFieldEncoding::Expanded
+    );
+    assert_eq!(
+        features
+            .for_field(&message.fields[1])
+            .unwrap()
+            .repeated_field_encoding,
+        RepeatedFieldEncoding::Packed
+    );
+    assert_eq!(
+        features
+            .for_oneof(&message.oneofs[0])
+            .unwrap()
+            .for_field(&message.oneofs[0].fields[0])
+            .unwrap()
+            .field_presence,
+        FieldPresence::Explicit
+    );
+
+    let input = "syntax = \"proto2\"; message M { repeated int32 a = 1 [packed = true]; }";
+    let def = parse(input).unwrap();
+    let field = ResolvedFeatures::for_file(&def)
+        .unwrap()
+        .for_message(&def.messages[0])
+        .unwrap()
+        .for_field(&def.messages[0].fields[0])
+        .unwrap();
+    assert_eq!(field.repeated_field_encoding, RepeatedFieldEncoding::Packed);
+}
+
+#[test]
+fn should_skip_language_specific_features() {
+    let input = "edition = \"2023\";
+    enum E {
+      option features.(pb.cpp).legacy_closed_enum = true;
+      option features = { enum_type: CLOSED [pb.java] { legacy_closed_enum: true } };
+      A = 0;
+    }";
+    let def = parse(input).unwrap();
+    let features = ResolvedFeatures::for_file(&def)
+        .unwrap()
+        .for_enum(&def.enums[0])
+        .unwrap();
+    assert_eq!(features.enum_type, EnumType::Closed);
+}
+
+#[test]
+fn should_merge_feature_set() {
+    let defaults = ResolvedFeatures::defaults(protoparse::ast::Syntax::V3);
+    let merged = defaults.merge(&FeatureSet {
+        utf8_validation: Some(Utf8Validation::None),
+        ..FeatureSet::default()
+    });
+    assert_eq!(merged.utf8_validation, Utf8Validation::None);
+    assert_eq!(merged.field_presence, defaults.field_presence);
+}
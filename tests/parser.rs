@@ -3,7 +3,9 @@ extern crate protoparse;
 use std::path::Path;
 
 use protoparse::ast::*;
-use protoparse::parser::{parse, parse_from_file};
+use protoparse::error::ProtoParseError;
+use protoparse::parser::{parse, parse_from_file, parse_recovering};
+use protoparse::span::Span;
 
 #[test]
 fn should_parse_syntax() {
@@ -13,6 +15,46 @@ fn should_parse_syntax() {
     assert_eq!(result.syntax, Syntax::V3);
 }
 
+#[test]
+fn should_parse_proto2_syntax() {
+    let input = "syntax = \"proto2\";".to_string();
+    let result = parse(&input).unwrap();
+
+    assert_eq!(result.syntax, Syntax::V2);
+}
+
+#[test]
+fn should_default_to_proto2_without_syntax() {
+    let input = "package foo;".to_string();
+    let result = parse(&input).unwrap();
+
+    assert_eq!(result.syntax, Syntax::V2);
+    assert_eq!(result.packages.len(), 1);
+}
+
+#[test]
+fn should_not_parse_unknown_syntax() {
+    let input = "syntax = \"proto4\";".to_string();
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn should_parse_edition() {
+    let input = "edition = \"2023\";".to_string();
+    let result = parse(&input).unwrap();
+    assert_eq!(result.syntax, Syntax::Edition(Edition::Edition2023));
+
+    let input = "edition = \"2024\";".to_string();
+    let result = parse(&input).unwrap();
+    assert_eq!(result.syntax, Syntax::Edition(Edition::Edition2024));
+}
+
+#[test]
+fn should_not_parse_unknown_edition() {
+    let input = "edition = \"1999\";".to_string();
+    assert!(parse(&input).is_err());
+}
+
 #[test]
 fn should_parse_unmodified_import() {
     let input = min_file() + "import \"foobar.proto\";";
@@ -83,12 +125,11 @@ fn parse_option_with_simple_ident_and_number_value() {
     let result = parse(&input).unwrap();
 
     assert_eq!(result.options.len(), 1);
-    assert_eq!(result.options[0].full_ident.idents.len(), 1);
     assert_eq!(
-        result.options[0].full_ident.idents[0],
-        "my_option".to_string()
+        result.options[0].name,
+        vec![OptionNamePart::new("my_option")]
     );
-    assert_eq!(result.options[0].constant, ConstantValue::NumberValue(42.0));
+    assert_eq!(result.options[0].constant, ConstantValue::IntValue(42));
 }
 
 #[test]
@@ -135,16 +176,17 @@ fn parse_option_with_full_ident_name() {
     let result = parse(&input).unwrap();
 
     assert_eq!(result.options.len(), 1);
-    assert_eq!(result.options[0].full_ident.idents.len(), 4);
     assert_eq!(
-        result.options[0].full_ident.idents[0],
-        "my_option".to_string()
+        result.options[0].name,
+        vec![
+            OptionNamePart::new("my_option"),
+            OptionNamePart::new("full"),
+            OptionNamePart::new("ident"),
+            OptionNamePart::new("name")
+        ]
     );
-    assert_eq!(result.options[0].full_ident.idents[1], "full".to_string());
-    assert_eq!(result.options[0].full_ident.idents[2], "ident".to_string());
-    assert_eq!(result.options[0].full_ident.idents[3], "name".to_string());
 
-    assert_eq!(result.options[0].constant, ConstantValue::NumberValue(42.0));
+    assert_eq!(result.options[0].constant, ConstantValue::IntValue(42));
 }
 
 #[test]
@@ -154,16 +196,68 @@ fn parse_option_with_full_ident_name_in_paren() {
     let result = parse(&input).unwrap();
 
     assert_eq!(result.options.len(), 1);
-    assert_eq!(result.options[0].full_ident.idents.len(), 4);
     assert_eq!(
-        result.options[0].full_ident.idents[0],
-        "my_option".to_string()
+        result.options[0].name,
+        vec![OptionNamePart::extension(&[
+            "my_option",
+            "full",
+            "ident",
+            "name"
+        ])]
+    );
+
+    assert_eq!(result.options[0].constant, ConstantValue::IntValue(42));
+}
+
+#[test]
+fn parse_option_with_custom_option_sub_field() {
+    let input = min_file() + "option (my.ext).sub.field = 42;";
+    let result = parse(&input).unwrap();
+
+    assert_eq!(
+        result.options[0].name,
+        vec![
+            OptionNamePart::extension(&["my", "ext"]),
+            OptionNamePart::new("sub"),
+            OptionNamePart::new("field")
+        ]
     );
-    assert_eq!(result.options[0].full_ident.idents[1], "full".to_string());
-    assert_eq!(result.options[0].full_ident.idents[2], "ident".to_string());
-    assert_eq!(result.options[0].full_ident.idents[3], "name".to_string());
+    assert_eq!(result.options[0].name_text(), "(my.ext).sub.field");
+}
+
+#[test]
+fn parse_option_with_extensions_after_dots() {
+    let input = "edition = \"2023\";
+    option features.(pb.cpp).legacy_closed_enum = true;
+    option (a).(b).c = 1;
+    option (.foo.bar) = 2;";
+    let result = parse(input).unwrap();
 
-    assert_eq!(result.options[0].constant, ConstantValue::NumberValue(42.0));
+    assert_eq!(
+        result.options[0].name,
+        vec![
+            OptionNamePart::new("features"),
+            OptionNamePart::extension(&["pb", "cpp"]),
+            OptionNamePart::new("legacy_closed_enum")
+        ]
+    );
+    assert_eq!(
+        result.options[1].name,
+        vec![
+            OptionNamePart::extension(&["a"]),
+            OptionNamePart::extension(&["b"]),
+            OptionNamePart::new("c")
+        ]
+    );
+    assert_eq!(
+        result.options[2].name,
+        vec![OptionNamePart {
+            name: FullIdent::new(vec!["foo".to_string(), "bar".to_string()]),
+            extension: true,
+            fully_qualified: true,
+        }]
+    );
+    assert_eq!(result.options[2].name_text(), "(.foo.bar)");
 }
 
 #[test]
@@ -172,7 +266,7 @@ fn parse_option_with_plus_prefixed_number() {
     let result = parse(&input).unwrap();
 
     assert_eq!(result.options.len(), 1);
-    assert_eq!(result.options[0].constant, ConstantValue::NumberValue(42.0));
+    assert_eq!(result.options[0].constant, ConstantValue::IntValue(42));
 }
 
 #[test]
@@ -181,12 +275,105 @@ fn parse_option_with_minus_prefixed_number() {
     let result = parse(&input).unwrap();
 
     assert_eq!(result.options.len(), 1);
+    assert_eq!(result.options[0].constant, ConstantValue::IntValue(-42));
+}
+
+#[test]
+fn parse_option_number_literals() {
+    let input = min_file()
+        + "option max_size = 5000000000;
+    option max_u64 = 18446744073709551615;
+    option min_i64 = -9223372036854775808;
+    option hex = 0x1F;
+    option oct = 017;
+    option ratio = 1.5e10;
+    option neg_ratio = -0.25;
+    option pos_inf = inf;
+    option neg_inf = -inf;
+    option not_a_number = nan;";
+    let result = parse(&input).unwrap();
+
     assert_eq!(
         result.options[0].constant,
-        ConstantValue::NumberValue(-42.0)
+        ConstantValue::IntValue(5_000_000_000)
+    );
+    assert_eq!(
+        result.options[1].constant,
+        ConstantValue::IntValue(u64::MAX as i128)
+    );
+    assert_eq!(
+        result.options[2].constant,
+        ConstantValue::IntValue(i64::MIN as i128)
+    );
+    assert_eq!(result.options[3].constant, ConstantValue::IntValue(31));
+    assert_eq!(result.options[4].constant, ConstantValue::IntValue(15));
+    assert_eq!(
+        result.options[5].constant,
+        ConstantValue::FloatValue(1.5e10)
+    );
+    assert_eq!(result.options[6].constant, ConstantValue::FloatValue(-0.25));
+    assert_eq!(
+        result.options[7].constant,
+        ConstantValue::FloatValue(f64::INFINITY)
+    );
+    assert_eq!(
+        result.options[8].constant,
+        ConstantValue::FloatValue(f64::NEG_INFINITY)
+    );
+    match result.options[9].constant {
+        ConstantValue::FloatValue(f) => assert!(f.is_nan()),
+        ref other => panic!("float expected, got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_bytes_default_with_invalid_utf8() {
+    let input = proto2_file()
+        + r#"message M {
+      optional bytes raw = 1 [default = "\xde\xad" '\276\357'];
+      optional string text = 2 [default = "café"];
+    }"#;
+    let result = parse(&input).unwrap();
+    let fields = &result.messages[0].fields;
+
+    assert_eq!(
+        fields[0].default,
+        Some(ConstantValue::BytesValue(vec![0xde, 0xad, 0xbe, 0xef]))
+    );
+    assert_eq!(
+        fields[1].default,
+        Some(ConstantValue::StringValue("café".to_string()))
+    );
+    assert_eq!(
+        fields[1].default.as_ref().unwrap().as_bytes(),
+        Some("café".as_bytes())
+    );
+}
+
+#[test]
+fn parse_field_with_hex_number_and_float_default() {
+    let input = proto2_file() + "message M { optional double d = 0x10 [default = -1.5e3]; }";
+    let result = parse(&input).unwrap();
+
+    assert_eq!(result.messages[0].fields[0].field_number, 16);
+    assert_eq!(
+        result.messages[0].fields[0].default,
+        Some(ConstantValue::FloatValue(-1500.0))
     );
 }
 
+#[test]
+fn parse_not_field_number_out_of_range() {
+    let input = min_file() + "message M { int32 a = 0; }";
+    assert!(parse(&input).is_err());
+
+    let input = min_file() + "message M { int32 a = 536870912; }";
+    assert!(parse(&input).is_err());
+
+    let input = min_file() + "message M { int32 a = 1.5; }";
+    assert!(parse(&input).is_err());
+}
+
 #[test]
 fn parse_empty_service() {
     let input = min_file()
@@ -286,6 +473,201 @@ fn parse_service_with_3_rpcs() {
     assert_eq!(result.services[0].rpcs[2].response_type.idents[0], "Rsp3");
 }
 
+#[test]
+fn parse_service_and_rpc_options() {
+    let input = min_file()
+        + "service Greeter {
+      option deprecated = true;
+      rpc SayHello(HelloRequest) returns (HelloReply) {
+        option (my.rpc_opt) = 3;
+        option idempotency_level = NO_SIDE_EFFECTS;
+      }
+      rpc SayBye(ByeRequest) returns (ByeReply) {};
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let service = &result.services[0];
+    assert_eq!(service.options.len(), 1);
+    assert_eq!(service.options[0].constant, ConstantValue::BoolValue(true));
+    assert_eq!(service.rpcs.len(), 2);
+    assert_eq!(service.rpcs[0].options.len(), 2);
+    assert_eq!(
+        service.rpcs[0].options[0].name,
+        vec![OptionNamePart::extension(&["my", "rpc_opt"])]
+    );
+    assert_eq!(
+        service.rpcs[0].options[1].constant,
+        ConstantValue::IdentValue(FullIdent::new(vec!["NO_SIDE_EFFECTS".to_string()]))
+    );
+    assert_eq!(service.rpcs[1].options.len(), 0);
+}
+
+#[test]
+fn parse_rpc_with_aggregate_option() {
+    let input = min_file()
+        + "service Library {
+      rpc GetShelf(GetShelfRequest) returns (Shelf) {
+        option (google.api.http) = { get: \"/v1/{name=shelves/*}\" body: \"*\" };
+      }
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let option = &result.services[0].rpcs[0].options[0];
+    assert_eq!(
+        option.name,
+        vec![OptionNamePart::extension(&["google", "api", "http"])]
+    );
+    let fields = match &option.constant {
+        ConstantValue::AggregateValue(fields) => fields,
+        other => panic!("aggregate expected, got {:?}", other),
+    };
+    assert_eq!(fields.len(), 2);
+    assert_eq!(fields[0].name, FullIdent::new(vec!["get".to_string()]));
+    assert!(!fields[0].extension);
+    assert_eq!(
+        fields[0].value,
+        ConstantValue::StringValue("/v1/{name=shelves/*}".to_string())
+    );
+    assert_eq!(fields[1].name, FullIdent::new(vec!["body".to_string()]));
+    assert_eq!(fields[1].value, ConstantValue::StringValue("*".to_string()));
+
+    let span = fields[0].span;
+    assert_eq!(
+        &input[span.start..span.end],
+        "get: \"/v1/{name=shelves/*}\""
+    );
+    let span = fields[1].name.span;
+    assert_eq!(&input[span.start..span.end], "body");
+}
+
+#[test]
+fn parse_nested_aggregate_option() {
+    let input = min_file()
+        + "option (my.opt) = {
+      name: \"foo\",
+      count: -3;
+      kind: KIND_A
+      nested { flag: true }
+      nested: < flag: false >
+      tags: [\"a\", \"b\"]
+      [ext.field]: 1
+      empty: []
+    };";
+
+    let result = parse(&input).unwrap();
+
+    let fields = match &result.options[0].constant {
+        ConstantValue::AggregateValue(fields) => fields,
+        other => panic!("aggregate expected, got {:?}", other),
+    };
+    assert_eq!(fields.len(), 8);
+    assert_eq!(fields[1].value, ConstantValue::IntValue(-3));
+    assert_eq!(
+        fields[2].value,
+        ConstantValue::IdentValue(FullIdent::new(vec!["KIND_A".to_string()]))
+    );
+
+    //repeated keys are kept in order
+    assert_eq!(fields[3].name, FullIdent::new(vec!["nested".to_string()]));
+    assert_eq!(fields[4].name, FullIdent::new(vec!["nested".to_string()]));
+    let flag = |field: &AggregateField| match &field.value {
+        ConstantValue::AggregateValue(nested) if nested.len() == 1 => {
+            assert_eq!(nested[0].name, FullIdent::new(vec!["flag".to_string()]));
+            nested[0].value.clone()
+        }
+        other => panic!("aggregate with flag expected, got {:?}", other),
+    };
+    assert_eq!(flag(&fields[3]), ConstantValue::BoolValue(true));
+    assert_eq!(flag(&fields[4]), ConstantValue::BoolValue(false));
+
+    assert_eq!(
+        fields[5].value,
+        ConstantValue::ListValue(vec![
+            ConstantValue::StringValue("a".to_string()),
+            ConstantValue::StringValue("b".to_string()),
+        ])
+    );
+    assert!(fields[6].extension);
+    assert_eq!(
+        fields[6].name,
+        FullIdent::new(vec!["ext".to_string(), "field".to_string()])
+    );
+    assert_eq!(fields[7].value, ConstantValue::ListValue(Vec::new()));
+}
+
+#[test]
+fn parse_not_aggregate_scalar_without_colon() {
+    let input = min_file() + "option (my.opt) = { name \"foo\" };";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_streaming_rpcs() {
+    let input = min_file()
+        + "service Chat {
+      rpc Unary(Msg) returns (Msg);
+      rpc Upload(stream Chunk) returns (Summary);
+      rpc Subscribe(Topic) returns (stream Event);
+      rpc Talk(stream foo.Msg) returns (stream foo.Msg) {}
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let rpcs = &result.services[0].rpcs;
+    assert!(!rpcs[0].client_streaming);
+    assert!(!rpcs[0].server_streaming);
+
+    assert!(rpcs[1].client_streaming);
+    assert!(!rpcs[1].server_streaming);
+    assert_eq!(rpcs[1].request_type.idents, vec!["Chunk".to_string()]);
+
+    assert!(!rpcs[2].client_streaming);
+    assert!(rpcs[2].server_streaming);
+    assert_eq!(rpcs[2].response_type.idents, vec!["Event".to_string()]);
+
+    assert!(rpcs[3].client_streaming);
+    assert!(rpcs[3].server_streaming);
+    assert_eq!(
+        rpcs[3].request_type.idents,
+        vec!["foo".to_string(), "Msg".to_string()]
+    );
+}
+
+#[test]
+fn parse_rpc_fully_qualified_types() {
+    let input = min_file()
+        + "service S {
+      rpc A(.google.protobuf.Empty) returns (.foo.Bar);
+      rpc B(stream .foo.Bar) returns (foo.Bar);
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let rpcs = &result.services[0].rpcs;
+    assert_eq!(
+        rpcs[0].request_type.idents,
+        vec!["google", "protobuf", "Empty"]
+    );
+    assert!(rpcs[0].request_fully_qualified);
+    assert_eq!(rpcs[0].response_type.idents, vec!["foo", "Bar"]);
+    assert!(rpcs[0].response_fully_qualified);
+
+    assert!(rpcs[1].client_streaming);
+    assert!(rpcs[1].request_fully_qualified);
+    assert!(!rpcs[1].response_fully_qualified);
+}
+
+#[test]
+fn parse_not_rpc_body_with_field() {
+    let input = min_file()
+        + "service Greeter {
+      rpc SayHello(HelloRequest) returns (HelloReply) { string foo = 1; }
+    }";
+    assert!(parse(&input).is_err());
+}
+
 #[test]
 fn parse_empty_message() {
     let input = min_file() + "message EmptyMessage {}";
@@ -312,22 +694,22 @@ fn parse_message_with_fields() {
     assert_eq!(result.messages[0].fields.len(), 4);
     assert_eq!(result.messages[0].fields[0].field_type, Type::String);
     assert_eq!(result.messages[0].fields[0].name, "foo");
-    assert_eq!(result.messages[0].fields[0].repeated, false);
+    assert!(!result.messages[0].fields[0].is_repeated());
     assert_eq!(result.messages[0].fields[0].field_number, 1);
 
     assert_eq!(result.messages[0].fields[1].field_type, Type::String);
     assert_eq!(result.messages[0].fields[1].name, "bar");
-    assert_eq!(result.messages[0].fields[1].repeated, true);
+    assert!(result.messages[0].fields[1].is_repeated());
     assert_eq!(result.messages[0].fields[1].field_number, 2);
 
     assert_eq!(result.messages[0].fields[2].field_type, Type::Double);
     assert_eq!(result.messages[0].fields[2].name, "baz");
-    assert_eq!(result.messages[0].fields[2].repeated, false);
+    assert!(!result.messages[0].fields[2].is_repeated());
     assert_eq!(result.messages[0].fields[2].field_number, 3);
 
     assert_eq!(result.messages[0].fields[3].field_type, Type::Bool);
     assert_eq!(result.messages[0].fields[3].name, "boo");
-    assert_eq!(result.messages[0].fields[3].repeated, false);
+    assert!(!result.messages[0].fields[3].is_repeated());
     assert_eq!(result.messages[0].fields[3].field_number, 4);
 }
 
@@ -346,58 +728,1354 @@ fn parse_message_with_keyword_identifier() {
     assert_eq!(result.messages[0].fields.len(), 1);
     assert_eq!(result.messages[0].fields[0].field_type, Type::String);
     assert_eq!(result.messages[0].fields[0].name, "message");
-    assert_eq!(result.messages[0].fields[0].repeated, false);
+    assert!(!result.messages[0].fields[0].is_repeated());
     assert_eq!(result.messages[0].fields[0].field_number, 1);
 }
 
 #[test]
-fn parse_simple_proto_file() {
-    let input = r#"syntax = "proto3";
+fn should_allow_all_keywords_as_field_names() {
+    let keywords = [
+        "syntax",
+        "import",
+        "package",
+        "service",
+        "rpc",
+        "returns",
+        "message",
+        "public",
+        "weak",
+        "option",
+        "enum",
+        "true",
+        "false",
+        "map",
+        "repeated",
+        "oneof",
+        "reserved",
+        "to",
+        "max",
+        "stream",
+        "required",
+        "optional",
+        "group",
+        "edition",
+        "extend",
+        "extensions",
+        "double",
+        "float",
+        "int32",
+        "int64",
+        "uint32",
+        "uint64",
+        "sint32",
+        "sint64",
+        "fixed32",
+        "fixed64",
+        "sfixed32",
+        "sfixed64",
+        "bool",
+        "string",
+        "bytes",
+    ];
+    let fields: Vec<String> = keywords
+        .iter()
+        .enumerate()
+        .map(|(i, k)| format!("  string {} = {};\n", k, i + 1))
+        .collect();
+    let input = min_file() + "message Foo {\n" + &fields.concat() + "}";
 
-    option go_package = "github.com/some/protoForGo";
+    let result = parse(&input).unwrap();
 
-    package gateway;
+    let names: Vec<&str> = result.messages[0]
+        .fields
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect();
+    assert_eq!(names, keywords);
+}
 
-    service GatewayService {
-      rpc GetGreeting(GetGreetingReq) returns (GetGreetingRsp) {}
+#[test]
+fn should_allow_keywords_as_names_and_type_references() {
+    let input = min_file()
+        + "package service.rpc;
+    message service {
+      message map {}
+      service.map map = 1;
+      map m = 2;
+      map<string, service> entries = 3;
+      enum enum { message = 0; enum = 1; max = 2; }
+      enum option { true = 0; }
     }
+    message stream {
+      stream.enum e = 1 [(oneof.option) = message];
+      .service.rpc.service s = 2;
+    }";
 
-    message GetGreetingReq {
-      string YourName = 1;
-    }
+    let result = parse(&input).unwrap();
 
-    message GetGreetingRsp {
-      string Greeting = 1;
-  }"#
-    .to_string();
+    assert_eq!(result.packages[0].full_ident.idents, ["service", "rpc"]);
+    let message = &result.messages[0];
+    assert_eq!(message.name, "service");
+    assert_eq!(message.nested_messages[0].name, "map");
+    let reference = |idents: &[&str], fully_qualified| Type::Reference {
+        full_ident: FullIdent::new(idents.iter().map(|s| s.to_string()).collect()),
+        fully_qualified,
+    };
+    assert_eq!(
+        message.fields[0].field_type,
+        reference(&["service", "map"], false)
+    );
+    assert_eq!(message.fields[0].name, "map");
+    assert_eq!(message.fields[1].field_type, reference(&["map"], false));
+    assert_eq!(
+        message.fields[2].field_type,
+        Type::Map {
+            key: Box::new(Type::String),
+            value: Box::new(reference(&["service"], false)),
+        }
+    );
+    let values: Vec<&str> = message.nested_enums[0]
+        .values
+        .iter()
+        .map(|v| v.name.as_str())
+        .collect();
+    assert_eq!(values, ["message", "enum", "max"]);
+    assert_eq!(message.nested_enums[1].values[0].name, "true");
+
+    let fields = &result.messages[1].fields;
+    assert_eq!(fields[0].field_type, reference(&["stream", "enum"], false));
+    assert_eq!(fields[0].options[0].name_text(), "(oneof.option)");
+    assert_eq!(
+        fields[0].options[0].constant,
+        ConstantValue::IdentValue(FullIdent::new(vec!["message".to_string()]))
+    );
+    assert_eq!(
+        fields[1].field_type,
+        reference(&["service", "rpc", "service"], true)
+    );
+}
+
+#[test]
+fn parse_message_with_reference_fields() {
+    let input = min_file()
+        + "message Envelope {
+      HelloRequest req = 1;
+      .google.protobuf.Timestamp ts = 2;
+      repeated foo.Bar bars = 3;
+    }";
 
     let result = parse(&input).unwrap();
 
-    assert_eq!(result.options.len(), 1);
-    assert_eq!(result.packages.len(), 1);
-    assert_eq!(result.services.len(), 1);
-    assert_eq!(result.messages.len(), 2);
+    let fields = &result.messages[0].fields;
+    assert_eq!(fields.len(), 3);
+    assert_eq!(
+        fields[0].field_type,
+        Type::Reference {
+            full_ident: FullIdent::new(vec!["HelloRequest".to_string()]),
+            fully_qualified: false,
+        }
+    );
+    assert_eq!(fields[0].name, "req");
+
+    assert_eq!(
+        fields[1].field_type,
+        Type::Reference {
+            full_ident: FullIdent::new(vec![
+                "google".to_string(),
+                "protobuf".to_string(),
+                "Timestamp".to_string()
+            ]),
+            fully_qualified: true,
+        }
+    );
+    assert_eq!(fields[1].name, "ts");
+    assert_eq!(fields[1].field_number, 2);
+
+    assert_eq!(
+        fields[2].field_type,
+        Type::Reference {
+            full_ident: FullIdent::new(vec!["foo".to_string(), "Bar".to_string()]),
+            fully_qualified: false,
+        }
+    );
+    assert!(fields[2].is_repeated());
 }
 
 #[test]
-fn should_parse_from_file_simple_example() {
-    parse_from_file(Path::new("tests/testdata/simple.proto")).unwrap();
+fn parse_not_reference_with_trailing_dot() {
+    let input = min_file() + "message Broken { foo. bar = 1; }";
+    assert!(parse(&input).is_err());
 }
 
 #[test]
-#[should_panic]
-fn should_error_if_file_does_not_exist() {
-    parse_from_file(Path::new("does/not/exist.proto")).unwrap();
+fn parse_map_fields() {
+    let input = min_file()
+        + "message Maps {
+      map<string, Project> projects = 1;
+      map<int32, string> names = 2;
+      map<bool, .foo.Bar> flags = 3;
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let fields = &result.messages[0].fields;
+    assert_eq!(fields.len(), 3);
+    assert_eq!(fields[0].name, "projects");
+    assert_eq!(
+        fields[0].field_type,
+        Type::Map {
+            key: Box::new(Type::String),
+            value: Box::new(Type::Reference {
+                full_ident: FullIdent::new(vec!["Project".to_string()]),
+                fully_qualified: false,
+            }),
+        }
+    );
+    assert_eq!(
+        fields[1].field_type,
+        Type::Map {
+            key: Box::new(Type::Int32),
+            value: Box::new(Type::String),
+        }
+    );
+    assert_eq!(fields[1].field_number, 2);
+    assert_eq!(
+        fields[2].field_type,
+        Type::Map {
+            key: Box::new(Type::Bool),
+            value: Box::new(Type::Reference {
+                full_ident: FullIdent::new(vec!["foo".to_string(), "Bar".to_string()]),
+                fully_qualified: true,
+            }),
+        }
+    );
 }
 
-//taken from: https://github.com/grpc/grpc-go/tree/master/examples/helloworld
 #[test]
-fn should_parse_helloworld_example() {
-    parse_from_file(Path::new("tests/testdata/helloworld.proto")).unwrap();
+fn parse_not_map_with_invalid_key_type() {
+    for key in ["float", "double", "bytes", "Message", ".foo.Message"].iter() {
+        let input = min_file() + &format!("message M {{ map<{}, string> m = 1; }}", key);
+        assert!(parse(&input).is_err(), "key type {} accepted", key);
+    }
 }
 
-// helper methods
+#[test]
+fn parse_not_repeated_map() {
+    let input = min_file() + "message M { repeated map<string, string> m = 1; }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_not_map_of_maps() {
+    let input = min_file() + "message M { map<string, map<string, string>> m = 1; }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_oneof() {
+    let input = min_file()
+        + "message SampleMessage {
+      int32 id = 1;
+      oneof test_oneof {
+        option (my.oneof_opt) = true;
+        string name = 4;
+        SubMessage sub_message = 9;
+      }
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let message = &result.messages[0];
+    assert_eq!(message.fields.len(), 1);
+    assert_eq!(message.oneofs.len(), 1);
+    assert_eq!(message.oneofs[0].name, "test_oneof");
+    assert_eq!(message.oneofs[0].options.len(), 1);
+    assert_eq!(
+        message.oneofs[0].options[0].constant,
+        ConstantValue::BoolValue(true)
+    );
+    assert_eq!(message.oneofs[0].fields.len(), 2);
+    assert_eq!(message.oneofs[0].fields[0].name, "name");
+    assert_eq!(message.oneofs[0].fields[0].field_number, 4);
+    assert_eq!(message.oneofs[0].fields[1].name, "sub_message");
+    assert_eq!(message.oneofs[0].fields[1].field_number, 9);
+}
+
+#[test]
+fn parse_not_repeated_oneof_field() {
+    let input = min_file() + "message M { oneof o { repeated string s = 1; } }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_not_map_oneof_field() {
+    let input = min_file() + "message M { oneof o { map<string, string> m = 1; } }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_message_reserved() {
+    let input = min_file()
+        + "message Foo {
+      reserved 2, 15, 9 to 11, 40 to max;
+      reserved \"foo\", \"bar\";
+      string baz = 1;
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let reserved = &result.messages[0].reserved;
+    assert_eq!(reserved.len(), 2);
+    assert_eq!(
+        reserved_ranges(&reserved[0]),
+        [(2, 2), (15, 15), (9, 11), (40, 536_870_911)]
+    );
+    assert!(reserved[0].names.is_empty());
+    assert!(reserved[1].ranges.is_empty());
+    assert_eq!(reserved[1].names, ["foo", "bar"]);
+
+    let span = reserved[0].span;
+    assert_eq!(
+        &input[span.start..span.end],
+        "reserved 2, 15, 9 to 11, 40 to max;"
+    );
+    let span = reserved[0].ranges[3].span;
+    assert_eq!(&input[span.start..span.end], "40 to max");
+}
+
+fn reserved_ranges(reserved: &Reserved) -> Vec<(i32, i32)> {
+    reserved.ranges.iter().map(|r| (r.start, r.end)).collect()
+}
+
+#[test]
+fn parse_not_field_with_reserved_number() {
+    let input = min_file() + "message Foo { string a = 10; reserved 9 to 11; }";
+    assert!(parse(&input).is_err());
+
+    let input = min_file() + "message Foo { reserved 5; oneof o { string a = 5; } }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_not_field_with_reserved_name() {
+    let input = min_file() + "message Foo { reserved \"foo\"; string foo = 1; }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_not_invalid_reserved_range() {
+    let input = min_file() + "message Foo { reserved 11 to 9; }";
+    assert!(parse(&input).is_err());
+
+    let input = min_file() + "message Foo { reserved 0; }";
+    assert!(parse(&input).is_err());
+
+    let input = min_file() + "message Foo { reserved 1, \"foo\"; }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_enum_reserved() {
+    let input = min_file()
+        + "enum Foo {
+      reserved -5 to -1, 2, 15 to max;
+      reserved \"FOO\";
+      BAR = 0;
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let reserved = &result.enums[0].reserved;
+    assert_eq!(
+        reserved_ranges(&reserved[0]),
+        [(-5, -1), (2, 2), (15, i32::MAX)]
+    );
+    assert_eq!(reserved[1].names, ["FOO"]);
+}
+
+#[test]
+fn parse_not_enum_value_with_reserved_number() {
+    let input = min_file() + "enum Foo { reserved 1 to 3; BAR = 2; }";
+    assert!(parse(&input).is_err());
+
+    let input = min_file() + "enum Foo { reserved \"BAR\"; BAR = 0; }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_field_options() {
+    let input = min_file()
+        + "message WithOptions {
+      string old = 1 [deprecated = true, json_name = \"x\"];
+      string email = 2 [(validate.rules).string.min_len = 1];
+      int32 plain = 3;
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let fields = &result.messages[0].fields;
+    assert_eq!(fields[0].options.len(), 2);
+    assert_eq!(
+        fields[0].options[0].name,
+        vec![OptionNamePart::new("deprecated")]
+    );
+    assert_eq!(
+        fields[0].options[0].constant,
+        ConstantValue::BoolValue(true)
+    );
+    assert_eq!(
+        fields[0].options[1].name,
+        vec![OptionNamePart::new("json_name")]
+    );
+    assert_eq!(
+        fields[0].options[1].constant,
+        ConstantValue::StringValue("x".to_string())
+    );
+
+    assert_eq!(fields[1].options.len(), 1);
+    assert_eq!(
+        fields[1].options[0].name,
+        vec![
+            OptionNamePart::extension(&["validate", "rules"]),
+            OptionNamePart::new("string"),
+            OptionNamePart::new("min_len")
+        ]
+    );
+    assert_eq!(fields[1].options[0].constant, ConstantValue::IntValue(1));
+
+    assert_eq!(fields[2].options.len(), 0);
+}
+
+#[test]
+fn parse_not_unclosed_field_options() {
+    let input = min_file() + "message M { string s = 1 [deprecated = true; }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_enum() {
+    let input = min_file()
+        + "enum Corpus {
+      UNIVERSAL = 0;
+      WEB = 1;
+      IMAGES = 2;
+    }";
+
+    let result = parse(&input).unwrap();
+
+    assert_eq!(result.enums.len(), 1);
+    assert_eq!(result.enums[0].name, "Corpus");
+    assert_eq!(result.enums[0].values.len(), 3);
+    assert_eq!(result.enums[0].values[0].name, "UNIVERSAL");
+    assert_eq!(result.enums[0].values[0].number, 0);
+    assert_eq!(result.enums[0].values[1].name, "WEB");
+    assert_eq!(result.enums[0].values[1].number, 1);
+    assert_eq!(result.enums[0].values[2].name, "IMAGES");
+    assert_eq!(result.enums[0].values[2].number, 2);
+}
+
+#[test]
+fn parse_enum_with_negative_values() {
+    let input = min_file()
+        + "enum Signed {
+      ZERO = 0;
+      MINUS_ONE = -1;
+      MIN = -2147483648;
+    }";
+
+    let result = parse(&input).unwrap();
+
+    assert_eq!(result.enums[0].values[1].number, -1);
+    assert_eq!(result.enums[0].values[2].number, i32::MIN);
+}
+
+#[test]
+fn parse_not_enum_with_out_of_range_value() {
+    let input = min_file() + "enum TooBig { BIG = 2147483648; }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_enum_value_options() {
+    let input = min_file()
+        + "enum WithOptions {
+      OLD = 0 [deprecated = true];
+      NEW = 1 [(my.opt) = \"foo\", other = 42];
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let values = &result.enums[0].values;
+    assert_eq!(values[0].options.len(), 1);
+    assert_eq!(
+        values[0].options[0].name,
+        vec![OptionNamePart::new("deprecated")]
+    );
+    assert_eq!(
+        values[0].options[0].constant,
+        ConstantValue::BoolValue(true)
+    );
+
+    assert_eq!(values[1].options.len(), 2);
+    assert_eq!(
+        values[1].options[0].name,
+        vec![OptionNamePart::extension(&["my", "opt"])]
+    );
+    assert_eq!(
+        values[1].options[0].constant,
+        ConstantValue::StringValue("foo".to_string())
+    );
+    assert_eq!(values[1].options[1].constant, ConstantValue::IntValue(42));
+}
+
+#[test]
+fn parse_enum_in_message() {
+    let input = min_file()
+        + "message SearchRequest {
+      string query = 1;
+      enum Corpus {
+        UNIVERSAL = 0;
+        WEB = 1;
+      }
+      int32 page_number = 2;
+    }";
+
+    let result = parse(&input).unwrap();
+
+    assert_eq!(result.enums.len(), 0);
+    assert_eq!(result.messages[0].fields.len(), 2);
+    assert_eq!(result.messages[0].nested_enums.len(), 1);
+    assert_eq!(result.messages[0].nested_enums[0].name, "Corpus");
+    assert_eq!(result.messages[0].nested_enums[0].values.len(), 2);
+}
+
+#[test]
+fn parse_nested_messages() {
+    let input = min_file()
+        + "message Outer {
+      message Inner {
+        message Innermost {
+          int64 ival = 1;
+        }
+        enum Kind {
+          A = 0;
+        }
+        Innermost deep = 1;
+      }
+      message Second {}
+      Inner inner = 1;
+      Outer.Inner scoped = 2;
+    }";
+
+    let result = parse(&input).unwrap();
+
+    assert_eq!(result.messages.len(), 1);
+    let outer = &result.messages[0];
+    assert_eq!(outer.fields.len(), 2);
+    assert_eq!(outer.nested_messages.len(), 2);
+    assert_eq!(outer.nested_messages[0].name, "Inner");
+    assert_eq!(outer.nested_messages[1].name, "Second");
+
+    let inner = &outer.nested_messages[0];
+    assert_eq!(inner.fields.len(), 1);
+    assert_eq!(inner.nested_enums.len(), 1);
+    assert_eq!(inner.nested_enums[0].name, "Kind");
+    assert_eq!(inner.nested_messages.len(), 1);
+    assert_eq!(inner.nested_messages[0].name, "Innermost");
+    assert_eq!(inner.nested_messages[0].fields[0].name, "ival");
+
+    assert_eq!(
+        outer.fields[1].field_type,
+        Type::Reference {
+            full_ident: FullIdent::new(vec!["Outer".to_string(), "Inner".to_string()]),
+            fully_qualified: false,
+        }
+    );
+}
+
+#[test]
+fn parse_message_and_enum_options() {
+    let input = min_file()
+        + "message WithOptions {
+      option (my.opt) = 3;
+      option deprecated = true;
+      string foo = 1;
+      enum Aliased {
+        option allow_alias = true;
+        UNKNOWN = 0;
+        STARTED = 1;
+        RUNNING = 1;
+      }
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let message = &result.messages[0];
+    assert_eq!(message.options.len(), 2);
+    assert_eq!(message.options[0].name_text(), "(my.opt)");
+    assert_eq!(message.options[0].constant, ConstantValue::IntValue(3));
+    assert_eq!(message.fields.len(), 1);
+
+    let enum_def = &message.nested_enums[0];
+    assert_eq!(enum_def.options.len(), 1);
+    assert!(enum_def.options[0].is_named("allow_alias"));
+    assert_eq!(enum_def.values.len(), 3);
+}
+
+#[test]
+fn parse_proto2_labels_and_defaults() {
+    let input = proto2_file()
+        + "message SearchRequest {
+      required string query = 1;
+      optional int32 page_number = 2 [default = 1];
+      optional Corpus corpus = 3 [default = WEB, deprecated = true];
+      repeated int32 samples = 4;
+      map<string, string> tags = 5;
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let fields = &result.messages[0].fields;
+    assert_eq!(fields[0].label, Some(FieldLabel::Required));
+    assert_eq!(fields[0].default, None);
+    assert_eq!(fields[1].label, Some(FieldLabel::Optional));
+    assert_eq!(fields[1].default, Some(ConstantValue::IntValue(1)));
+    assert_eq!(fields[1].options.len(), 0);
+    assert_eq!(
+        fields[2].default,
+        Some(ConstantValue::IdentValue(FullIdent::new(vec![
+            "WEB".to_string()
+        ])))
+    );
+    assert_eq!(fields[2].options.len(), 1);
+    assert_eq!(fields[3].label, Some(FieldLabel::Repeated));
+    assert!(fields[3].is_repeated());
+    assert_eq!(fields[4].label, None);
+}
+
+#[test]
+fn parse_not_proto2_field_without_label() {
+    let input = proto2_file() + "message M { string s = 1; }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_not_proto3_required_or_default() {
+    let input = min_file() + "message M { required string s = 1; }";
+    assert!(parse(&input).is_err());
+
+    let input = min_file() + "message M { int32 i = 1 [default = 5]; }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_not_default_on_repeated_field() {
+    let input = proto2_file() + "message M { repeated int32 i = 1 [default = 5]; }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_proto2_defaults_of_all_types() {
+    let input = proto2_file()
+        + "message M {
+      optional uint64 a = 1 [default = 18446744073709551615];
+      optional sint32 b = 2 [default = -2147483648];
+      optional float c = 3 [default = 1];
+      optional double d = 4 [default = -inf];
+      optional bool e = 5 [default = true];
+      optional bytes f = 6 [default = \"\\xff\"];
+      optional Kind g = 7 [default = KIND_A];
+    }";
+
+    assert!(parse(&input).is_ok());
+}
+
+#[test]
+fn parse_not_default_of_wrong_type() {
+    let error = parse(&(proto2_file() + "message M { optional int32 a = 1 [default = \"x\"]; }"))
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "field a: default value must be an integer"
+    );
+
+    let error = parse(&(proto2_file() + "message M { optional uint32 a = 1 [default = -1]; }"))
+        .unwrap_err();
+    assert_eq!(error.to_string(), "field a: default value -1 out of range");
+
+    for field in [
+        "optional int32 a = 1 [default = 2147483648]",
+        "optional int64 a = 1 [default = 1.5]",
+        "optional double a = 1 [default = \"1\"]",
+        "optional bool a = 1 [default = 1]",
+        "optional string a = 1 [default = 1]",
+        "optional bytes a = 1 [default = FOO]",
+        "optional Kind a = 1 [default = 1]",
+        "optional Kind a = 1 [default = Kind.A]",
+    ] {
+        let input = proto2_file() + "message M { " + field + "; }";
+        assert!(parse(&input).is_err(), "{}", field);
+    }
+}
+
+#[test]
+fn parse_proto2_group() {
+    let input = proto2_file()
+        + "message SearchResponse {
+      repeated group Result = 1 {
+        required string url = 2;
+        optional string title = 3;
+      }
+      oneof kind {
+        group Special = 4 { optional int32 id = 5; }
+      }
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let message = &result.messages[0];
+    assert_eq!(message.fields.len(), 1);
+    assert_eq!(message.fields[0].name, "result");
+    assert_eq!(
+        message.fields[0].field_type,
+        Type::Group("Result".to_string())
+    );
+    assert_eq!(message.fields[0].label, Some(FieldLabel::Repeated));
+    assert_eq!(message.fields[0].field_number, 1);
+
+    assert_eq!(message.oneofs[0].fields[0].name, "special");
+    assert_eq!(message.oneofs[0].fields[0].label, None);
+
+    assert_eq!(message.nested_messages.len(), 2);
+    assert_eq!(message.nested_messages[0].name, "Result");
+    assert_eq!(message.nested_messages[0].fields.len(), 2);
+    assert_eq!(message.nested_messages[0].fields[0].name, "url");
+    assert_eq!(message.nested_messages[1].name, "Special");
+}
+
+#[test]
+fn parse_not_group_in_proto3() {
+    let input = min_file() + "message M { repeated group Result = 1 {} }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_edition_features_and_reserved_idents() {
+    let input = "edition = \"2023\";
+    option features.enum_type = CLOSED;
+    message M {
+      option features.field_presence = IMPLICIT;
+      int32 a = 1 [features.field_presence = EXPLICIT];
+      reserved foo, bar;
+      enum E {
+        option features.enum_type = OPEN;
+        E_UNKNOWN = 0;
+      }
+    }"
+    .to_string();
+
+    let result = parse(&input).unwrap();
+
+    assert_eq!(result.options.len(), 1);
+    assert!(result.options[0].is_named("features.enum_type"));
+    assert_eq!(result.messages[0].options.len(), 1);
+    assert_eq!(result.messages[0].fields[0].options.len(), 1);
+    assert_eq!(result.messages[0].reserved[0].names, ["foo", "bar"]);
+}
+
+#[test]
+fn parse_not_features_outside_editions() {
+    let input = min_file() + "option features.field_presence = EXPLICIT;";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_not_unknown_feature() {
+    let input = "edition = \"2023\"; option features.field_presence = MAYBE;".to_string();
+    assert!(parse(&input).is_err());
+
+    let input = "edition = \"2023\"; option features.no_such_feature = TRUE;".to_string();
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_not_labels_or_groups_in_editions() {
+    let input = "edition = \"2023\"; message M { optional int32 a = 1; }".to_string();
+    assert!(parse(&input).is_err());
+
+    let input = "edition = \"2023\"; message M { required int32 a = 1; }".to_string();
+    assert!(parse(&input).is_err());
+
+    let input = "edition = \"2023\"; message M { repeated group G = 1 {} }".to_string();
+    assert!(parse(&input).is_err());
+
+    let input = "edition = \"2023\"; message M { reserved \"foo\"; }".to_string();
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_proto3_optional_fields() {
+    let input = min_file()
+        + "message Patch {
+      optional int32 count = 1;
+      optional string name = 2;
+      int32 plain = 3;
+      oneof choice { string a = 4; }
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let message = &result.messages[0];
+    assert!(message.fields[0].proto3_optional);
+    assert_eq!(message.fields[0].label, Some(FieldLabel::Optional));
+    assert!(message.fields[1].proto3_optional);
+    assert!(!message.fields[2].proto3_optional);
+    assert!(!message.oneofs[0].fields[0].proto3_optional);
+
+    let synthetic = message.synthetic_oneofs();
+    assert_eq!(synthetic.len(), 2);
+    assert_eq!(synthetic[0].name, "_count");
+    assert_eq!(synthetic[0].field.name, "count");
+    assert_eq!(synthetic[1].name, "_name");
+    assert_eq!(synthetic[1].field.field_number, 2);
+}
+
+#[test]
+fn parse_proto3_optional_synthetic_oneof_name_conflicts() {
+    let input = min_file()
+        + "message Conflicts {
+      optional int32 foo = 1;
+      int32 _foo = 2;
+      oneof X_foo { string a = 3; }
+      optional int32 _bar = 4;
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let synthetic = result.messages[0].synthetic_oneofs();
+    assert_eq!(synthetic[0].name, "XX_foo");
+    assert_eq!(synthetic[1].name, "X_bar");
+}
+
+#[test]
+fn parse_proto3_optional_synthetic_oneof_conflicts_with_oneof_field() {
+    let input = min_file()
+        + "message M {
+      optional int32 x = 1;
+      oneof o { int32 _x = 2; }
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let synthetic = result.messages[0].synthetic_oneofs();
+    assert_eq!(synthetic[0].name, "X_x");
+}
+
+#[test]
+fn parse_proto2_optional_is_not_proto3_optional() {
+    let input = proto2_file() + "message M { optional int32 a = 1; }";
+    let result = parse(&input).unwrap();
+
+    assert!(!result.messages[0].fields[0].proto3_optional);
+    assert_eq!(result.messages[0].synthetic_oneofs().len(), 0);
+}
+
+#[test]
+fn parse_extensions_and_extend() {
+    let input = proto2_file()
+        + "package foo;
+    message Extendable {
+      optional int32 a = 1;
+      extensions 100 to 199, 300;
+      extensions 1000 to max [(my.decl) = true];
+      extend Extendable {
+        optional int32 nested_ext = 150;
+      }
+    }
+    extend Extendable {
+      optional string file_ext = 100;
+      repeated group Grouped = 300 { optional int32 g = 1; }
+    }
+    extend .google.protobuf.FieldOptions {
+      optional bool my_option = 50000;
+    }";
+
+    let result = parse(&input).unwrap();
+
+    let message = &result.messages[0];
+    let ranges: Vec<(i32, i32)> = message
+        .extensions
+        .iter()
+        .flat_map(|e| e.ranges.iter())
+        .map(|r| (r.start, r.end))
+        .collect();
+    assert_eq!(ranges, [(100, 199), (300, 300), (1000, 536_870_911)]);
+    assert_eq!(message.extensions[0].options.len(), 0);
+    assert_eq!(message.extensions[1].options.len(), 1);
+    assert_eq!(message.extends.len(), 1);
+    assert_eq!(message.extends[0].fields[0].name, "nested_ext");
+
+    assert_eq!(result.extends.len(), 2);
+    assert_eq!(
+        result.extends[0].extendee,
+        FullIdent::new(vec!["Extendable".to_string()])
+    );
+    assert!(!result.extends[0].fully_qualified);
+    assert_eq!(result.extends[0].fields.len(), 2);
+    assert_eq!(
+        result.extends[0].fields[1].field_type,
+        Type::Group("Grouped".to_string())
+    );
+    assert_eq!(result.messages.len(), 2);
+    assert_eq!(result.messages[1].name, "Grouped");
+
+    assert!(result.extends[1].fully_qualified);
+    assert_eq!(
+        result.extends[1].extendee.idents,
+        vec![
+            "google".to_string(),
+            "protobuf".to_string(),
+            "FieldOptions".to_string()
+        ]
+    );
+}
+
+#[test]
+fn parse_not_extension_outside_of_range() {
+    let input = proto2_file()
+        + "message Extendable { extensions 100 to 199; }
+    extend Extendable { optional int32 bad = 200; }";
+    assert!(parse(&input).is_err());
+
+    let input = proto2_file()
+        + "package foo;
+    message Outer {
+      message Extendable { extensions 100 to 199; }
+      extend Extendable { optional int32 bad = 5; }
+    }";
+    assert!(parse(&input).is_err());
+
+    let input = proto2_file()
+        + "package foo;
+    message Extendable { extensions 100 to 199; }
+    extend .foo.Extendable { optional int32 bad = 5; }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_not_field_in_extension_range() {
+    let input = proto2_file() + "message M { extensions 1 to 10; optional int32 a = 5; }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_not_extensions_in_proto3() {
+    let input = min_file() + "message M { extensions 100 to 199; }";
+    assert!(parse(&input).is_err());
+}
+
+#[test]
+fn parse_proto3_custom_option_extend() {
+    let input = min_file()
+        + "import \"google/protobuf/descriptor.proto\";
+    extend google.protobuf.MessageOptions {
+      string my_option = 51234;
+    }";
+
+    let result = parse(&input).unwrap();
+    assert_eq!(result.extends[0].fields[0].name, "my_option");
+    assert_eq!(result.extends[0].fields[0].field_number, 51234);
+}
+
+#[test]
+fn parse_simple_proto_file() {
+    let input = r#"syntax = "proto3";
+
+    option go_package = "github.com/some/protoForGo";
+
+    package gateway;
+
+    service GatewayService {
+      rpc GetGreeting(GetGreetingReq) returns (GetGreetingRsp) {}
+    }
+
+    message GetGreetingReq {
+      string YourName = 1;
+    }
+
+    message GetGreetingRsp {
+      string Greeting = 1;
+  }"#
+    .to_string();
+
+    let result = parse(&input).unwrap();
+
+    assert_eq!(result.options.len(), 1);
+    assert_eq!(result.packages.len(), 1);
+    assert_eq!(result.services.len(), 1);
+    assert_eq!(result.messages.len(), 2);
+}
+
+#[test]
+fn should_attach_spans_to_ast_nodes() {
+    let input = "syntax = \"proto3\";
+import \"a.proto\";
+message Foo {
+  int32 id = 1 [deprecated = true];
+  enum E { A = 0; }
+}
+";
+    let result = parse(input).unwrap();
+    assert_eq!(result.imports[0].span, Span::new(19, 36, 2, 1));
+
+    let message = &result.messages[0];
+    assert_eq!(message.span, Span::new(37, input.len() - 1, 3, 1));
+    assert_eq!(
+        &input[message.span.start..message.span.end],
+        &input[37..input.len() - 1]
+    );
+
+    let field = &message.fields[0];
+    assert_eq!(
+        &input[field.span.start..field.span.end],
+        "int32 id = 1 [deprecated = true];"
+    );
+    assert_eq!((field.span.line, field.span.column), (4, 3));
+    let option = &field.options[0];
+    assert_eq!(
+        &input[option.span.start..option.span.end],
+        "deprecated = true"
+    );
+
+    let value = &message.nested_enums[0].values[0];
+    assert_eq!(&input[value.span.start..value.span.end], "A = 0;");
+    assert_eq!((value.span.line, value.span.column), (5, 12));
+}
+
+#[test]
+fn should_attach_spans_to_names() {
+    let input = "syntax = \"proto3\";
+package foo.bar;
+message Foo {
+  .foo.Bar b = 1 [(my.opt).x = y.Z];
+}
+service S { rpc Get(stream foo.Req) returns (.foo.Res); }
+";
+    let result = parse(input).unwrap();
+    let text = |span: Span| &input[span.start..span.end];
+
+    assert_eq!(text(result.packages[0].full_ident.span), "foo.bar");
+    let field = &result.messages[0].fields[0];
+    match &field.field_type {
+        Type::Reference { full_ident, .. } => assert_eq!(text(full_ident.span), "foo.Bar"),
+        other => panic!("reference expected, got {:?}", other),
+    }
+    let option = &field.options[0];
+    assert_eq!(text(option.name[0].name.span), "my.opt");
+    assert_eq!(text(option.name[1].name.span), "x");
+    match &option.constant {
+        ConstantValue::IdentValue(value) => assert_eq!(text(value.span), "y.Z"),
+        other => panic!("identifier expected, got {:?}", other),
+    }
+    let rpc = &result.services[0].rpcs[0];
+    assert_eq!(text(rpc.request_type.span), "foo.Req");
+    assert_eq!(text(rpc.response_type.span), "foo.Res");
+}
+
+#[test]
+fn should_report_error_location() {
+    let input = min_file() + "\nmessage Foo {\n  int32 id = 1\n}";
+    let error = parse(&input).unwrap_err();
+    assert_eq!(error.span(), Some(Span::new(48, 49, 4, 1)));
+
+    let input = min_file() + "\nmessage Foo {\n  reserved 1;\n  int32 id = 1;\n}";
+    let error = parse(&input).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Foo: number 1 of id is reserved on line 3"
+    );
+    let span = error.span().unwrap();
+    assert_eq!(&input[span.start..span.end], "int32 id = 1;");
+    assert_eq!((span.line, span.column), (4, 3));
+}
+
+#[test]
+fn should_return_unexpected_token_error() {
+    let input = min_file() + "\nmessage Foo {\n  int32 id = 1\n}";
+    let error = parse(&input).unwrap_err();
+    assert_eq!(
+        error,
+        ProtoParseError::UnexpectedToken {
+            found: "`}`".to_string(),
+            expected: vec!["`[`".to_string(), "`;`".to_string()],
+            span: Some(Span::new(48, 49, 4, 1)),
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "unexpected `}`, expected one of `[` or `;`"
+    );
+
+    let error = parse(&(min_file() + "enum E { A = 0; 1 }")).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "unexpected integer `1`, expected one of enum value, `option`, `reserved` or `}`"
+    );
+}
+
+#[test]
+fn should_render_error_snippet() {
+    let input = min_file() + "\nmessage Foo {\n\tint32 id = 1 int32\n}";
+    let error = parse(&input).unwrap_err();
+    assert_eq!(
+        error.render(&input, "foo.proto"),
+        "error: unexpected `int32`, expected one of `[` or `;`
+ --> foo.proto:3:15
+  |
+3 | \tint32 id = 1 int32
+  | \t             ^^^^^
+"
+    );
+}
+
+#[test]
+fn should_render_error_at_end_after_lone_carriage_return() {
+    let input = "syntax = \"proto3\";\nmessage M {\r";
+    let error = parse(input).unwrap_err();
+    assert_eq!(
+        error.render(input, "foo.proto"),
+        "error: unexpected end of input, expected `}`
+ --> foo.proto:2:13
+  |
+2 | message M {
+  |             ^
+"
+    );
+}
+
+#[test]
+fn should_return_io_error() {
+    let error = parse_from_file(Path::new("does/not/exist.proto")).unwrap_err();
+    assert_eq!(
+        error,
+        ProtoParseError::Io {
+            path: "does/not/exist.proto".into(),
+            kind: std::io::ErrorKind::NotFound,
+        }
+    );
+    assert_eq!(
+        error.render("", "exist.proto"),
+        "error: cannot read does/not/exist.proto: entity not found\n --> does/not/exist.proto\n"
+    );
+}
+
+#[test]
+fn should_recover_and_report_all_errors() {
+    let input = min_file()
+        + "
+message A {
+  int32 a = ;
+  int32 b = 2;
+  int32 c = 3 [;
+}
+enum E { X = 0; Y = }
+message B { int32 ok = 1; }
+";
+    let (def, diagnostics) = parse_recovering(&input);
+    let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "unexpected `;`, expected integer",
+            "unexpected `;`, expected one of identifier or `(`",
+            "unexpected `}`, expected integer",
+        ]
+    );
+    let lines: Vec<usize> = diagnostics.iter().map(|d| d.span().unwrap().line).collect();
+    assert_eq!(lines, vec![3, 5, 7]);
+
+    assert_eq!(def.syntax, Syntax::V3);
+    assert_eq!(def.messages.len(), 2);
+    assert_eq!(def.messages[0].fields.len(), 1);
+    assert_eq!(def.messages[0].fields[0].name, "b");
+    assert_eq!(def.messages[1].fields[0].name, "ok");
+    assert_eq!(def.enums[0].values.len(), 1);
+
+    assert_eq!(parse(&input).unwrap_err(), diagnostics[0]);
+}
+
+#[test]
+fn should_report_check_and_parse_errors_in_source_order() {
+    let input = "syntax = \"proto2\";
+message A { extensions 10 to 20; }
+extend A { optional int32 e = 1; }
+message B { optional int32 = 1; }
+";
+    let (_, diagnostics) = parse_recovering(input);
+    let lines: Vec<usize> = diagnostics.iter().map(|d| d.span().unwrap().line).collect();
+    assert_eq!(lines, vec![3, 4]);
+    assert_eq!(
+        parse(input).unwrap_err().to_string(),
+        "extension e = 1 is not in an extension range of A"
+    );
+}
+
+#[test]
+fn should_report_all_extension_and_feature_errors() {
+    let input = "edition = \"2023\";
+option features.field_presence = MAYBE;
+message A {
+  extensions 10 to 20, 30 [features.unknown = X];
+  int32 x = 1 [features.enum_type = SOMETIMES];
+}
+extend A {
+  int32 e1 = 1;
+  int32 e2 = 15;
+  int32 e3 = 40;
+}
+";
+    let (_, diagnostics) = parse_recovering(input);
+    let lines: Vec<usize> = diagnostics.iter().map(|d| d.span().unwrap().line).collect();
+    assert_eq!(lines, vec![2, 4, 5, 8, 10]);
+    assert_eq!(
+        diagnostics[3].to_string(),
+        "extension e1 = 1 is not in an extension range of A"
+    );
+}
+
+#[test]
+fn should_recover_from_errors_in_aggregate_values() {
+    let input = min_file() + "message A { option (x) = { a: }; int32 b = 1; } message B {}";
+    let (def, diagnostics) = parse_recovering(&input);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "unexpected `}`, expected constant"
+    );
+    assert_eq!(def.messages[0].fields[0].name, "b");
+    assert_eq!(def.messages[1].name, "B");
+}
+
+#[test]
+fn should_return_partial_def_on_unexpected_end_of_input() {
+    let input = min_file() + "message A { message B { int32 a = 1;";
+    let (def, diagnostics) = parse_recovering(&input);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "unexpected end of input, expected `}`"
+    );
+    assert_eq!(def.messages[0].nested_messages[0].fields[0].name, "a");
+}
+
+#[test]
+fn should_keep_nodes_with_semantic_errors() {
+    let input = min_file() + "message A { reserved 1; int32 a = 1; int32 b = 2 }";
+    let (def, diagnostics) = parse_recovering(&input);
+    let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "A: number 1 of a is reserved on line 1",
+            "unexpected `}`, expected one of `[` or `;`",
+        ]
+    );
+    assert_eq!(def.messages[0].fields.len(), 1);
+}
+
+#[test]
+fn should_parse_from_file_simple_example() {
+    parse_from_file(Path::new("tests/testdata/simple.proto")).unwrap();
+}
+
+#[test]
+#[should_panic]
+fn should_error_if_file_does_not_exist() {
+    parse_from_file(Path::new("does/not/exist.proto")).unwrap();
+}
+
+//taken from: https://github.com/grpc/grpc-go/tree/master/examples/helloworld
+#[test]
+fn should_parse_helloworld_example() {
+    parse_from_file(Path::new("tests/testdata/helloworld.proto")).unwrap();
+}
+
+#[test]
+fn should_attach_comments_in_helloworld_example() {
+    let def = parse_from_file(Path::new("tests/testdata/helloworld.proto")).unwrap();
+    let service = &def.services[0];
+    assert_eq!(
+        service.comments.leading.as_deref(),
+        Some(" The greeting service definition.\n")
+    );
+    assert!(service.comments.detached.is_empty());
+    assert_eq!(
+        service.rpcs[0].comments,
+        Comments {
+            leading: Some(" Sends a greeting\n".to_string()),
+            trailing: None,
+            detached: Vec::new(),
+        }
+    );
+    assert_eq!(
+        def.messages[0].comments.leading.as_deref(),
+        Some(" The request message containing the user's name.\n")
+    );
+    assert_eq!(
+        def.messages[1].comments.leading.as_deref(),
+        Some(" The response message containing the greetings\n")
+    );
+    assert_eq!(def.messages[1].fields[0].comments, Comments::default());
+}
+
+#[test]
+fn should_attach_trailing_and_detached_comments() {
+    let input = "syntax = \"proto2\";
+message Foo { // foo
+  // detached
+
+  /* leading */
+  optional int32 a = 1; // trailing a
+  optional group Bar = 2 {
+    // trailing bar
+  }
+}
+enum E {
+  X = 0;
+  // trailing x
+}"
+    .to_string();
+    let def = parse(&input).unwrap();
+    let message = &def.messages[0];
+    assert_eq!(message.comments.trailing.as_deref(), Some(" foo\n"));
+    assert_eq!(
+        message.fields[0].comments,
+        Comments {
+            leading: Some(" leading ".to_string()),
+            trailing: Some(" trailing a\n".to_string()),
+            detached: vec![" detached\n".to_string()],
+        }
+    );
+    assert_eq!(
+        message.fields[1].comments.trailing.as_deref(),
+        Some(" trailing bar\n")
+    );
+    assert_eq!(
+        message.nested_messages[0].comments,
+        message.fields[1].comments
+    );
+    assert_eq!(
+        def.enums[0].values[0].comments.trailing.as_deref(),
+        Some(" trailing x\n")
+    );
+}
+
+// helper methods
+
+fn proto2_file() -> String {
+    "syntax = \"proto2\";".to_string()
+}
 
 fn min_file() -> String {
-    return "syntax = \"proto3\";".to_string();
+    "syntax = \"proto3\";".to_string()
 }
@@ -0,0 +1,437 @@
+extern crate protoparse;
+
+use std::path::Path;
+
+use protoparse::ast::*;
+use protoparse::parser::{parse, parse_from_file};
+use protoparse::printer::{PrintConfig, print, print_with};
+use protoparse::span::Span;
+
+#[test]
+fn should_print_helloworld_example() {
+    let def = parse_from_file(Path::new("tests/testdata/helloworld.proto")).unwrap();
+
+    let printed = print(&def);
+
+    let source = std::fs::read_to_string("tests/testdata/helloworld.proto").unwrap();
+    //the only differences are the space before ( and the empty rpc body
+    let expected = source
+        .replace("SayHello (", "SayHello(")
+        .replace(") {}", ");");
+    assert_eq!(printed, expected);
+}
+
+#[test]
+fn should_print_canonical_form() {
+    let input = "syntax = \"proto2\";
+import \"z.proto\"; import public \"a.proto\";
+package foo;
+message Foo {
+  optional int32 a = 1 [default = -5];
+  // about b
+  repeated string long_name = 2; // trailing
+  extensions 100 to max;
+  option deprecated = true;
+  optional group Grp = 3 { optional int32 g = 1; }
+  message Inner {}
+  reserved 10 to 20, 30;
+  map<string, Foo> m = 4;
+}
+enum E { ZERO = 0; ONE = 1 [deprecated = true]; }
+service S { rpc Get (stream Foo) returns (.foo.Foo) {} }";
+
+    let printed = print(&parse(input).unwrap());
+
+    assert_eq!(
+        printed,
+        "syntax = \"proto2\";
+
+package foo;
+
+import public \"a.proto\";
+import \"z.proto\";
+
+message Foo {
+  option deprecated = true;
+
+  reserved 10 to 20, 30;
+
+  extensions 100 to max;
+
+  optional int32 a          = 1 [default = -5];
+  // about b
+  repeated string long_name = 2; // trailing
+
+  optional group Grp = 3 {
+    optional int32 g = 1;
+  }
+
+  message Inner {}
+
+  map<string, Foo> m = 4;
+}
+
+enum E {
+  ZERO = 0;
+  ONE  = 1 [deprecated = true];
+}
+
+service S {
+  rpc Get(stream Foo) returns (.foo.Foo);
+}
+"
+    );
+}
+
+#[test]
+fn should_apply_config() {
+    let input = "syntax = \"proto3\";
+import \"z.proto\";
+import \"a.proto\";
+message Foo {
+  int32 a = 1;
+  string long_name = 2 [deprecated = true, json_name = \"ln\"];
+}";
+    let config = PrintConfig {
+        indent: "\t".to_string(),
+        align_fields: false,
+        sort_imports: false,
+        max_width: 40,
+    };
+
+    let printed = print_with(&parse(input).unwrap(), &config);
+
+    assert_eq!(
+        printed,
+        "syntax = \"proto3\";
+
+import \"z.proto\";
+import \"a.proto\";
+
+message Foo {
+\tint32 a = 1;
+\tstring long_name = 2 [
+\t\tdeprecated = true,
+\t\tjson_name = \"ln\"
+\t];
+}
+"
+    );
+}
+
+#[test]
+fn should_wrap_long_aggregate_options() {
+    let input = "syntax = \"proto3\";
+option (http) = { get: \"/v1/things/{name}\" additional_bindings { post: \"/v1/things\" body: \"*\" } };";
+    let config = PrintConfig {
+        max_width: 50,
+        ..PrintConfig::default()
+    };
+
+    let printed = print_with(&parse(input).unwrap(), &config);
+
+    assert_eq!(
+        printed,
+        "syntax = \"proto3\";
+
+option (http) = {
+  get: \"/v1/things/{name}\"
+  additional_bindings {
+    post: \"/v1/things\"
+    body: \"*\"
+  }
+};
+"
+    );
+}
+
+#[test]
+fn should_keep_comments_attached() {
+    let input = "// header
+
+syntax = \"proto3\";
+message A {
+
+  // detached
+
+  // leading x
+  int32 x = 1;
+  int32 y = 2;
+  // trailing y
+  // over two lines
+
+  int32 z = 3; // trailing z
+}";
+    let def = parse(input).unwrap();
+
+    let printed = print(&def);
+    let reparsed = parse(&printed).unwrap();
+
+    assert_eq!(reparsed.comments, def.comments);
+    let fields = &reparsed.messages[0].fields;
+    for (field, expected) in fields.iter().zip(def.messages[0].fields.iter()) {
+        assert_eq!(field.comments, expected.comments);
+    }
+    assert_eq!(print(&reparsed), printed);
+}
+
+#[test]
+fn should_print_constants_that_parse_back_unchanged() {
+    let input = "syntax = \"proto2\";
+message Foo {
+  optional bytes b = 1 [default = \"\\001\\xff\\\"q\\\\\"];
+  optional string s = 2 [default = \"tab\\there \\u00e9\\n\"];
+  optional double d = 3 [default = -inf];
+  optional float f = 4 [default = 1.0, (x) = 1e-7, (y) = nan];
+  optional E e = 5 [default = max];
+  optional int64 i = 6 [default = -9223372036854775808];
+}";
+    let def = parse(input).unwrap();
+
+    let reparsed = parse(&print(&def)).unwrap();
+
+    for (field, expected) in reparsed.messages[0]
+        .fields
+        .iter()
+        .zip(def.messages[0].fields.iter())
+    {
+        match (&field.default, &expected.default) {
+            (Some(a), Some(b)) => assert_same_constant(a, b),
+            (a, b) => assert_eq!(a, b),
+        }
+        assert_eq!(field.options.len(), expected.options.len());
+        for (a, b) in field.options.iter().zip(expected.options.iter()) {
+            assert_same_constant(&a.constant, &b.constant);
+        }
+    }
+}
+
+//like assert_eq, nan is the same as nan
+fn assert_same_constant(a: &ConstantValue, b: &ConstantValue) {
+    match (a, b) {
+        (ConstantValue::FloatValue(x), ConstantValue::FloatValue(y)) if x.is_nan() => {
+            assert!(y.is_nan(), "{} != nan", y)
+        }
+        _ => assert_eq!(a, b),
+    }
+}
+
+#[test]
+fn should_print_reserved_names_as_identifiers_in_editions() {
+    let input = "edition = \"2023\";
+message Foo { reserved foo, bar; }
+enum E { reserved X; reserved 2 to max; A = 0; }";
+
+    let printed = print(&parse(input).unwrap());
+
+    assert_eq!(
+        printed,
+        "edition = \"2023\";
+
+message Foo {
+  reserved foo, bar;
+}
+
+enum E {
+  reserved X;
+  reserved 2 to max;
+
+  A = 0;
+}
+"
+    );
+}
+
+#[test]
+fn should_print_constructed_definition() {
+    let mut def = ProtoDef::new(Syntax::V3);
+    def.add_message(Message {
+        name: "Foo".to_string(),
+        fields: vec![Field {
+            name: "bar".to_string(),
+            field_type: Type::Reference {
+                full_ident: FullIdent::new(vec!["pkg".to_string(), "Bar".to_string()]),
+                fully_qualified: true,
+            },
+            label: Some(FieldLabel::Repeated),
+            field_number: 1,
+            default: None,
+            options: Vec::new(),
+            proto3_optional: false,
+            span: Span::default(),
+            comments: Comments {
+                leading: Some(" the bars\n".to_string()),
+                trailing: None,
+                detached: Vec::new(),
+            },
+        }],
+        nested_messages: Vec::new(),
+        nested_enums: Vec::new(),
+        oneofs: Vec::new(),
+        reserved: Vec::new(),
+        options: Vec::new(),
+        extensions: Vec::new(),
+        extends: Vec::new(),
+        span: Span::default(),
+        comments: Comments::default(),
+    });
+
+    assert_eq!(
+        print(&def),
+        "syntax = \"proto3\";
+
+message Foo {
+  // the bars
+  repeated .pkg.Bar bar = 1;
+}
+"
+    );
+}
+
+#[test]
+fn should_keep_comments_of_all_statements() {
+    let input = "syntax = \"proto2\";
+// the package
+package foo; // trailing package
+import \"a.proto\";
+// why we need b
+import \"b.proto\";
+option go_package = \"x\"; // go pkg
+// long option
+option (http) = { get: \"/v1/things/{name}\" additional_bindings { post: \"/v1/things\" } }; // trailing opt
+message Foo {
+  // old ids
+  reserved 10 to 20;
+  reserved \"bar\"; // old name
+  // for plugins
+  extensions 100 to 199, 300;
+
+  // detached
+
+  // explains the choice
+  oneof choice { // trailing oneof
+    option (o) = 1; // in oneof
+    int32 a = 1;
+  }
+  // extends itself
+  extend Foo {
+    optional int32 e = 100;
+  }
+}
+enum E {
+  // no longer used
+  reserved 5;
+  ZERO = 0;
+}";
+    let config = PrintConfig {
+        max_width: 50,
+        ..PrintConfig::default()
+    };
+
+    let printed = print_with(&parse(input).unwrap(), &config);
+
+    assert_eq!(
+        printed,
+        "syntax = \"proto2\";
+
+// the package
+package foo; // trailing package
+
+import \"a.proto\";
+// why we need b
+import \"b.proto\";
+
+option go_package = \"x\"; // go pkg
+// long option
+option (http) = {
+  get: \"/v1/things/{name}\"
+  additional_bindings { post: \"/v1/things\" }
+}; // trailing opt
+
+message Foo {
+  // old ids
+  reserved 10 to 20;
+  reserved \"bar\"; // old name
+
+  // for plugins
+  extensions 100 to 199, 300;
+
+  // detached
+
+  // explains the choice
+  oneof choice { // trailing oneof
+    option (o) = 1; // in oneof
+
+    int32 a = 1;
+  }
+
+  // extends itself
+  extend Foo {
+    optional int32 e = 100;
+  }
+}
+
+enum E {
+  // no longer used
+  reserved 5;
+
+  ZERO = 0;
+}
+"
+    );
+    assert_eq!(print_with(&parse(&printed).unwrap(), &config), printed);
+}
+
+#[test]
+fn should_keep_empty_comment_lines_at_the_end() {
+    let input = "// a
+//
+
+syntax = \"proto3\";
+// leading
+//
+message Foo { // trailing
+  //
+  // leading x
+  //
+  int32 x = 1;
+  // trailing x
+  //
+
+  int32 y = 2;
+}";
+    let def = parse(input).unwrap();
+
+    let printed = print(&def);
+    let reparsed = parse(&printed).unwrap();
+
+    assert_eq!(reparsed.comments.detached, [" a\n\n"]);
+    assert_eq!(reparsed.messages[0].comments, def.messages[0].comments);
+    let fields = &reparsed.messages[0].fields;
+    for (field, expected) in fields.iter().zip(def.messages[0].fields.iter()) {
+        assert_eq!(field.comments, expected.comments);
+    }
+    assert_eq!(print(&reparsed), printed);
+}
+
+#[test]
+fn should_print_extension_ranges_as_their_statements() {
+    let input = "syntax = \"proto2\";
+message Foo {
+  extensions 1 to 5, 10 to max [(x) = true];
+  extensions 7;
+}";
+
+    let printed = print(&parse(input).unwrap());
+
+    assert_eq!(
+        printed,
+        "syntax = \"proto2\";
+
+message Foo {
+  extensions 1 to 5, 10 to max [(x) = true];
+  extensions 7;
+}
+"
+    );
+}
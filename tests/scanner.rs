@@ -1,7 +1,8 @@
 extern crate protoparse;
 
-use protoparse::error::err;
-use protoparse::scanner::{Scanner, Token};
+use protoparse::error::ProtoParseError;
+use protoparse::scanner::{Scanner, SpannedToken, Token};
+use protoparse::span::Span;
 
 #[test]
 fn should_return_eof_for_empty_input() {
@@ -109,21 +110,21 @@ fn should_scan_enum() {
         Ok(Token::Ident("UNKNOWN".to_string()))
     );
     assert_eq!(scanner.next_token(), Ok(Token::Eq));
-    assert_eq!(scanner.next_token(), Ok(Token::DecimalLit(0)));
+    assert_eq!(scanner.next_token(), Ok(Token::IntLit(0)));
     assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
     assert_eq!(
         scanner.next_token(),
         Ok(Token::Ident("STARTED".to_string()))
     );
     assert_eq!(scanner.next_token(), Ok(Token::Eq));
-    assert_eq!(scanner.next_token(), Ok(Token::DecimalLit(1)));
+    assert_eq!(scanner.next_token(), Ok(Token::IntLit(1)));
     assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
     assert_eq!(
         scanner.next_token(),
         Ok(Token::Ident("RUNNING".to_string()))
     );
     assert_eq!(scanner.next_token(), Ok(Token::Eq));
-    assert_eq!(scanner.next_token(), Ok(Token::DecimalLit(2)));
+    assert_eq!(scanner.next_token(), Ok(Token::IntLit(2)));
     assert_eq!(scanner.next_token(), Ok(Token::LBracket));
     assert_eq!(scanner.next_token(), Ok(Token::LParen));
     assert_eq!(
@@ -174,7 +175,7 @@ fn should_scan_message() {
     assert_eq!(scanner.next_token(), Ok(Token::TInt64));
     assert_eq!(scanner.next_token(), Ok(Token::Ident("ival".to_string())));
     assert_eq!(scanner.next_token(), Ok(Token::Eq));
-    assert_eq!(scanner.next_token(), Ok(Token::DecimalLit(1)));
+    assert_eq!(scanner.next_token(), Ok(Token::IntLit(1)));
     assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
     assert_eq!(scanner.next_token(), Ok(Token::RCurly));
 
@@ -186,7 +187,7 @@ fn should_scan_message() {
     assert_eq!(scanner.next_token(), Ok(Token::Gt));
     assert_eq!(scanner.next_token(), Ok(Token::Ident("my_map".to_string())));
     assert_eq!(scanner.next_token(), Ok(Token::Eq));
-    assert_eq!(scanner.next_token(), Ok(Token::DecimalLit(2)));
+    assert_eq!(scanner.next_token(), Ok(Token::IntLit(2)));
     assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
     assert_eq!(scanner.next_token(), Ok(Token::RCurly));
     assert_eq!(scanner.next_token(), Ok(Token::EOF));
@@ -232,13 +233,36 @@ fn should_can_line_comment() {
     let mut scanner = Scanner::new(&input);
     assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
     assert_eq!(scanner.next_token(), Ok(Token::EOF));
+}
 
-    //not really correct, but since there is no single / in proto, assume
-    //it is a line comment
-    let input2 = "/a comment\n;".to_string();
-    let mut scanner2 = Scanner::new(&input2);
-    assert_eq!(scanner2.next_token(), Ok(Token::Semicolon));
-    assert_eq!(scanner2.next_token(), Ok(Token::EOF));
+#[test]
+fn should_skip_block_comment() {
+    let input = "/* message\n * int64 */message/**/;/* a * b ** / */".to_string();
+    let mut scanner = Scanner::new(&input);
+    assert_eq!(scanner.next_token(), Ok(Token::Message));
+    assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
+    assert_eq!(scanner.next_token(), Ok(Token::EOF));
+}
+
+#[test]
+fn should_return_err_on_unterminated_block_comment() {
+    let input = "message /* int64 *".to_string();
+    let mut scanner = Scanner::new(&input);
+    assert_eq!(scanner.next_token(), Ok(Token::Message));
+    assert_eq!(
+        scanner.next_token(),
+        lexical_at(Span::new(8, 18, 1, 9), "unterminated block comment")
+    );
+}
+
+#[test]
+fn should_return_err_on_lone_slash() {
+    let input = "/a comment\n;".to_string();
+    let mut scanner = Scanner::new(&input);
+    assert_eq!(
+        scanner.next_token(),
+        lexical_at(Span::new(0, 1, 1, 1), "unexpected character '/'")
+    );
 }
 
 // str literal tests
@@ -267,7 +291,83 @@ fn should_scan_str_literal_with_escaping() {
 fn should_return_err_on_unknown_escape() {
     let input = "\"\\k\"".to_string();
     let mut scanner = Scanner::new(&input);
-    assert_eq!(scanner.next_token(), err("Lexical error: unknown escaping"));
+    assert_eq!(
+        scanner.next_token(),
+        lexical_at(Span::new(0, 3, 1, 1), "unknown escaping")
+    );
+}
+
+#[test]
+fn should_scan_str_literal_escapes() {
+    let input = r#""\n\t\x41\101\\\'\?" "é\U0001F600é""#.to_string();
+    let mut scanner = Scanner::new(&input);
+    assert_eq!(
+        scanner.next_token(),
+        Ok(Token::StrLit("\n\tAA\\'?é😀é".to_string()))
+    );
+    assert_eq!(scanner.next_token(), Ok(Token::EOF));
+}
+
+#[test]
+fn should_scan_single_quoted_and_adjacent_str_literals() {
+    let input = "'say \"hi\"' \"a\"\n  'b';".to_string();
+    let mut scanner = Scanner::new(&input);
+    assert_eq!(
+        scanner.next_token(),
+        Ok(Token::StrLit("say \"hi\"ab".to_string()))
+    );
+    assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
+}
+
+#[test]
+fn should_join_str_literals_separated_by_comments() {
+    let input = "\"a\" // note\n /* more */ \"b\" // after\n;".to_string();
+    let mut scanner = Scanner::new(&input);
+    assert_eq!(scanner.next_token(), Ok(Token::StrLit("ab".to_string())));
+    assert_eq!(scanner.trailing_comment(), Some(" after\n"));
+    assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
+}
+
+#[test]
+fn should_scan_surrogate_pair_escape() {
+    let input = r#""\uD83D\uDE00""#.to_string();
+    let mut scanner = Scanner::new(&input);
+    assert_eq!(scanner.next_token(), Ok(Token::StrLit("😀".to_string())));
+
+    let input2 = r#""\uD83D""#.to_string();
+    let mut scanner2 = Scanner::new(&input2);
+    assert_eq!(
+        scanner2.next_token(),
+        lexical_at(
+            Span::new(0, 8, 1, 1),
+            "unpaired surrogate in unicode escape"
+        )
+    );
+}
+
+#[test]
+fn should_scan_invalid_utf8_as_bytes_literal() {
+    let input = r#""\xff\0a\377""#.to_string();
+    let mut scanner = Scanner::new(&input);
+    assert_eq!(
+        scanner.next_token(),
+        Ok(Token::BytesLit(vec![0xff, 0, b'a', 0xff]))
+    );
+}
+
+#[test]
+fn should_return_err_on_illegal_escapes() {
+    for (input, msg) in [
+        (r#""\x""#, "lexical error: illegal hex escape"),
+        (r#""\400""#, "lexical error: octal escape out of range"),
+        (r#""\u12""#, "lexical error: illegal unicode escape"),
+        (r#""\U00110000""#, "lexical error: illegal unicode escape"),
+        ("\"a\nb\"", "lexical error: line break in string literal"),
+    ] {
+        let mut scanner = Scanner::new(input);
+        let error = scanner.next_token().unwrap_err();
+        assert_eq!(error.to_string(), msg, "{}", input);
+    }
 }
 
 #[test]
@@ -276,7 +376,7 @@ fn should_return_err_on_non_closed_str_literal() {
     let mut scanner = Scanner::new(&input);
     assert_eq!(
         scanner.next_token(),
-        err("Lexical error: unclosed string literal")
+        lexical_at(Span::new(0, 12, 1, 1), "unclosed string literal")
     );
 }
 
@@ -296,3 +396,200 @@ fn should_scan_repeated() {
     assert_eq!(scanner.next_token(), Ok(Token::Repeated));
     assert_eq!(scanner.next_token(), Ok(Token::EOF));
 }
+
+#[test]
+fn should_scan_oneof() {
+    let input = "oneof test_oneof { string name = 4; }".to_string();
+    let mut scanner = Scanner::new(&input);
+
+    assert_eq!(scanner.next_token(), Ok(Token::OneOf));
+    assert_eq!(
+        scanner.next_token(),
+        Ok(Token::Ident("test_oneof".to_string()))
+    );
+    assert_eq!(scanner.next_token(), Ok(Token::LCurly));
+    assert_eq!(scanner.next_token(), Ok(Token::TString));
+    assert_eq!(scanner.next_token(), Ok(Token::Ident("name".to_string())));
+    assert_eq!(scanner.next_token(), Ok(Token::Eq));
+    assert_eq!(scanner.next_token(), Ok(Token::IntLit(4)));
+    assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
+    assert_eq!(scanner.next_token(), Ok(Token::RCurly));
+    assert_eq!(scanner.next_token(), Ok(Token::EOF));
+}
+
+#[test]
+fn should_scan_colon() {
+    let input = "{ get: \"/v1\" }".to_string();
+    let mut scanner = Scanner::new(&input);
+
+    assert_eq!(scanner.next_token(), Ok(Token::LCurly));
+    assert_eq!(scanner.next_token(), Ok(Token::Ident("get".to_string())));
+    assert_eq!(scanner.next_token(), Ok(Token::Colon));
+    assert_eq!(scanner.next_token(), Ok(Token::StrLit("/v1".to_string())));
+    assert_eq!(scanner.next_token(), Ok(Token::RCurly));
+    assert_eq!(scanner.next_token(), Ok(Token::EOF));
+}
+
+#[test]
+fn should_scan_stream() {
+    let input = "(stream Msg)".to_string();
+    let mut scanner = Scanner::new(&input);
+
+    assert_eq!(scanner.next_token(), Ok(Token::LParen));
+    assert_eq!(scanner.next_token(), Ok(Token::Stream));
+    assert_eq!(scanner.next_token(), Ok(Token::Ident("Msg".to_string())));
+    assert_eq!(scanner.next_token(), Ok(Token::RParen));
+    assert_eq!(scanner.next_token(), Ok(Token::EOF));
+}
+
+#[test]
+fn should_scan_int_literals() {
+    let input = "0 42 0x1F 0XfF 017 18446744073709551615".to_string();
+    let mut scanner = Scanner::new(&input);
+
+    assert_eq!(scanner.next_token(), Ok(Token::IntLit(0)));
+    assert_eq!(scanner.next_token(), Ok(Token::IntLit(42)));
+    assert_eq!(scanner.next_token(), Ok(Token::IntLit(31)));
+    assert_eq!(scanner.next_token(), Ok(Token::IntLit(255)));
+    assert_eq!(scanner.next_token(), Ok(Token::IntLit(15)));
+    assert_eq!(scanner.next_token(), Ok(Token::IntLit(u64::MAX)));
+    assert_eq!(scanner.next_token(), Ok(Token::EOF));
+}
+
+#[test]
+fn should_scan_float_literals() {
+    let input = "1.5 1.5e10 2E-3 1. .5 3e+2 -1.25".to_string();
+    let mut scanner = Scanner::new(&input);
+
+    assert_eq!(scanner.next_token(), Ok(Token::FloatLit(1.5)));
+    assert_eq!(scanner.next_token(), Ok(Token::FloatLit(1.5e10)));
+    assert_eq!(scanner.next_token(), Ok(Token::FloatLit(2e-3)));
+    assert_eq!(scanner.next_token(), Ok(Token::FloatLit(1.0)));
+    assert_eq!(scanner.next_token(), Ok(Token::FloatLit(0.5)));
+    assert_eq!(scanner.next_token(), Ok(Token::FloatLit(300.0)));
+    assert_eq!(scanner.next_token(), Ok(Token::Minus));
+    assert_eq!(scanner.next_token(), Ok(Token::FloatLit(1.25)));
+    assert_eq!(scanner.next_token(), Ok(Token::EOF));
+}
+
+#[test]
+fn should_scan_dot_before_ident() {
+    let input = ".foo.Bar".to_string();
+    let mut scanner = Scanner::new(&input);
+
+    assert_eq!(scanner.next_token(), Ok(Token::Dot));
+    assert_eq!(scanner.next_token(), Ok(Token::Ident("foo".to_string())));
+    assert_eq!(scanner.next_token(), Ok(Token::Dot));
+    assert_eq!(scanner.next_token(), Ok(Token::Ident("Bar".to_string())));
+    assert_eq!(scanner.next_token(), Ok(Token::EOF));
+}
+
+#[test]
+fn should_return_err_on_illegal_number_literals() {
+    for lit in [
+        "18446744073709551616",
+        "0x",
+        "0x1G",
+        "019",
+        "1e",
+        "1.5e+",
+        "12abc",
+    ]
+    .iter()
+    {
+        let input = lit.to_string();
+        let mut scanner = Scanner::new(&input);
+        assert!(scanner.next_token().is_err(), "{} accepted", lit);
+    }
+}
+
+#[test]
+fn should_track_token_spans() {
+    let input = "message Foo {\n  /* é */ string s = 1;\n}".to_string();
+    let mut scanner = Scanner::new(&input);
+    let expected = [
+        (Token::Message, Span::new(0, 7, 1, 1)),
+        (Token::Ident("Foo".to_string()), Span::new(8, 11, 1, 9)),
+        (Token::LCurly, Span::new(12, 13, 1, 13)),
+        (Token::TString, Span::new(25, 31, 2, 11)),
+        (Token::Ident("s".to_string()), Span::new(32, 33, 2, 18)),
+        (Token::Eq, Span::new(34, 35, 2, 20)),
+        (Token::IntLit(1), Span::new(36, 37, 2, 22)),
+        (Token::Semicolon, Span::new(37, 38, 2, 23)),
+        (Token::RCurly, Span::new(39, 40, 3, 1)),
+    ];
+    for (token, span) in expected {
+        assert_eq!(
+            scanner.next_spanned_token(),
+            Ok(SpannedToken { token, span })
+        );
+        assert_eq!(scanner.span(), span);
+    }
+}
+
+#[test]
+fn should_not_include_trailing_whitespace_in_str_literal_span() {
+    let input = "\"a\" \"b\"  ;".to_string();
+    let mut scanner = Scanner::new(&input);
+    assert_eq!(scanner.next_token(), Ok(Token::StrLit("ab".to_string())));
+    assert_eq!(scanner.span(), Span::new(0, 7, 1, 1));
+    assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
+    assert_eq!(scanner.span(), Span::new(9, 10, 1, 10));
+    assert_eq!(scanner.prev_span(), Span::new(0, 7, 1, 1));
+}
+
+#[test]
+fn should_attribute_comments_like_protoc() {
+    let input = "// detached\n\n// leading\nfoo; // trailing\nbar; /* block\n  * trailing */\n\n/* leading */ baz\n}"
+        .to_string();
+    let mut scanner = Scanner::new(&input);
+    assert_eq!(scanner.next_token(), Ok(Token::Ident("foo".to_string())));
+    assert_eq!(scanner.leading_comment(), Some(" leading\n"));
+    assert_eq!(scanner.detached_comments(), [" detached\n".to_string()]);
+    assert_eq!(scanner.trailing_comment(), None);
+    assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
+    assert_eq!(scanner.trailing_comment(), Some(" trailing\n"));
+    assert_eq!(scanner.next_token(), Ok(Token::Ident("bar".to_string())));
+    assert_eq!(scanner.leading_comment(), None);
+    assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
+    assert_eq!(scanner.trailing_comment(), Some(" block\n trailing "));
+    assert_eq!(scanner.next_token(), Ok(Token::Ident("baz".to_string())));
+    assert_eq!(scanner.leading_comment(), Some(" leading "));
+    assert!(scanner.detached_comments().is_empty());
+    assert_eq!(scanner.next_token(), Ok(Token::RCurly));
+}
+
+#[test]
+fn should_attach_comment_below_token_as_trailing_before_blank_line() {
+    let input = "foo;\n// trailing\n\n// leading\nbar;\n// also leading\nbaz;".to_string();
+    let mut scanner = Scanner::new(&input);
+    scanner.next_token().unwrap();
+    assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
+    assert_eq!(scanner.trailing_comment(), Some(" trailing\n"));
+    assert_eq!(scanner.next_token(), Ok(Token::Ident("bar".to_string())));
+    assert_eq!(scanner.leading_comment(), Some(" leading\n"));
+    assert_eq!(scanner.next_token(), Ok(Token::Semicolon));
+    assert_eq!(scanner.trailing_comment(), None);
+    assert_eq!(scanner.next_token(), Ok(Token::Ident("baz".to_string())));
+    assert_eq!(scanner.leading_comment(), Some(" also leading\n"));
+}
+
+#[test]
+fn should_not_attach_comments_to_closing_brace() {
+    let input = "{\n  // trailing\n\n  // detached\n}".to_string();
+    let mut scanner = Scanner::new(&input);
+    assert_eq!(scanner.next_token(), Ok(Token::LCurly));
+    assert_eq!(scanner.trailing_comment(), Some(" trailing\n"));
+    assert_eq!(scanner.next_token(), Ok(Token::RCurly));
+    assert_eq!(scanner.leading_comment(), None);
+    assert_eq!(scanner.detached_comments(), [" detached\n".to_string()]);
+}
+
+// helper methods
+
+fn lexical_at(span: Span, message: &str) -> Result<Token, ProtoParseError> {
+    Err(ProtoParseError::Lexical {
+        message: message.to_string(),
+        span: Some(span),
+    })
+}
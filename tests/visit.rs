@@ -0,0 +1,169 @@
+extern crate protoparse;
+
+use protoparse::ast::*;
+use protoparse::parser::parse;
+use protoparse::visit::*;
+
+fn example() -> ProtoDef {
+    let input = "syntax = \"proto2\";
+package pkg;
+option (file_opt) = { name: [ref.Value] };
+message Outer {
+  optional Inner inner = 1;
+  map<string, pkg.Other> others = 2;
+  oneof choice {
+    string s = 3;
+    group Grp = 4 { optional int32 g = 1; }
+  }
+  message Inner {
+    repeated .pkg.Outer outer = 1 [(field_opt) = CONST];
+  }
+  enum Kind { KIND_A = 0; KIND_B = 1; }
+  extend Other { optional int32 ext = 100; }
+}
+message Other { extensions 100 to 200; }
+service Svc {
+  rpc Call (Outer) returns (Other);
+}";
+    parse(input).unwrap()
+}
+
+#[derive(Default)]
+struct Collector<'ast> {
+    messages: Vec<&'ast str>,
+    fields: Vec<&'ast str>,
+    enum_values: Vec<&'ast str>,
+    idents: Vec<String>,
+    rpcs: usize,
+}
+
+impl<'ast> Visit<'ast> for Collector<'ast> {
+    fn visit_message(&mut self, node: &'ast Message) {
+        self.messages.push(&node.name);
+        visit_message(self, node);
+    }
+
+    fn visit_field(&mut self, node: &'ast Field) {
+        self.fields.push(&node.name);
+        visit_field(self, node);
+    }
+
+    fn visit_enum_value(&mut self, node: &'ast EnumValue) {
+        self.enum_values.push(&node.name);
+    }
+
+    fn visit_full_ident(&mut self, node: &'ast FullIdent) {
+        self.idents.push(node.idents.join("."));
+    }
+
+    fn visit_rpc(&mut self, node: &'ast Rpc) {
+        self.rpcs += 1;
+        visit_rpc(self, node);
+    }
+}
+
+#[test]
+fn should_visit_all_nodes() {
+    let def = example();
+    let mut collector = Collector::default();
+
+    collector.visit_proto_def(&def);
+
+    assert_eq!(collector.messages, ["Outer", "Grp", "Inner", "Other"]);
+    assert_eq!(
+        collector.fields,
+        ["inner", "others", "s", "grp", "g", "outer", "ext"]
+    );
+    assert_eq!(collector.enum_values, ["KIND_A", "KIND_B"]);
+    assert_eq!(collector.rpcs, 1);
+    assert_eq!(
+        collector.idents,
+        [
+            "pkg",
+            "file_opt",
+            "name",
+            "ref.Value",
+            "Outer",
+            "Other",
+            "Inner",
+            "pkg.Other",
+            "pkg.Outer",
+            "field_opt",
+            "CONST",
+            "Other",
+        ]
+    );
+}
+
+struct TopLevelMessages(Vec<String>);
+
+impl Visit<'_> for TopLevelMessages {
+    //not walking the message skips the nested ones
+    fn visit_message(&mut self, node: &Message) {
+        self.0.push(node.name.clone());
+    }
+}
+
+#[test]
+fn should_not_visit_children_without_walk() {
+    let def = example();
+    let mut visitor = TopLevelMessages(Vec::new());
+
+    visitor.visit_proto_def(&def);
+
+    assert_eq!(visitor.0, ["Outer", "Other"]);
+}
+
+//moves all types of package pkg to package renamed
+struct RenamePackage;
+
+impl VisitMut for RenamePackage {
+    fn visit_full_ident_mut(&mut self, node: &mut FullIdent) {
+        if node.idents.first().is_some_and(|i| i == "pkg") {
+            node.idents[0] = "renamed".to_string();
+        }
+    }
+
+    fn visit_enum_value_mut(&mut self, node: &mut EnumValue) {
+        node.name = node.name.to_lowercase();
+        visit_enum_value_mut(self, node);
+    }
+}
+
+#[test]
+fn should_mutate_nodes() {
+    let mut def = example();
+
+    RenamePackage.visit_proto_def_mut(&mut def);
+
+    assert_eq!(def.packages[0].full_ident.idents, ["renamed"]);
+    let outer = &def.messages[0];
+    assert_eq!(
+        outer.fields[1].field_type,
+        Type::Map {
+            key: Box::new(Type::String),
+            value: Box::new(Type::Reference {
+                full_ident: FullIdent::new(vec!["renamed".to_string(), "Other".to_string()]),
+                fully_qualified: false,
+            }),
+        }
+    );
+    let inner = outer
+        .nested_messages
+        .iter()
+        .find(|m| m.name == "Inner")
+        .unwrap();
+    assert_eq!(
+        inner.fields[0].field_type,
+        Type::Reference {
+            full_ident: FullIdent::new(vec!["renamed".to_string(), "Outer".to_string()]),
+            fully_qualified: true,
+        }
+    );
+    let values: Vec<&str> = outer.nested_enums[0]
+        .values
+        .iter()
+        .map(|v| v.name.as_str())
+        .collect();
+    assert_eq!(values, ["kind_a", "kind_b"]);
+}